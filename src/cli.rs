@@ -1,13 +1,27 @@
-use clap::{Args, Parser, Subcommand};
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
 
 #[derive(Parser)]
-#[command(version, about = "Divera reports", long_about = None)]
+#[command(
+    version,
+    about = "Divera reports",
+    long_about = None,
+    after_help = "Exit codes: 1 general failure, 65 unexpected divera response, \
+                  69 error reported by divera, 75 temporary failure, 77 authentication failure"
+)]
 #[clap(propagate_version = true)]
 pub struct Cli {
     /// Config path
     #[arg(global = true, short, long)]
     pub config: Option<String>,
 
+    /// Skip malformed reports instead of aborting
+    #[arg(global = true, long)]
+    pub lenient: bool,
+
+    /// Number of skipped reports which is tolerated before failing. Implies --lenient
+    #[arg(global = true, long)]
+    pub max_errors: Option<usize>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -25,49 +39,199 @@ pub enum Commands {
 }
 
 #[derive(Debug, Args)]
+#[command(group(ArgGroup::new("divera_secret").args(["divera_password", "divera_password_file"])))]
+#[command(group(ArgGroup::new("webdav_secret").args(["webdav_password", "webdav_password_file"])))]
 pub struct Init {
-    /// Username for divera247
+    /// Ask for missing values, check the login and the webdav connection before writing the config
+    #[arg(short, long)]
+    pub interactive: bool,
+
+    /// Url of the divera api
     #[arg(long)]
-    pub divera_username: String,
+    pub divera_url: Option<String>,
+    /// Username for divera247, needs a password unless it is asked for interactively
+    #[arg(long, required_unless_present_any = ["divera_access_key", "interactive"])]
+    pub divera_username: Option<String>,
     /// Password for divera247
-    #[arg(long)]
-    pub divera_password: String,
+    #[arg(long, requires = "divera_username")]
+    pub divera_password: Option<String>,
+    /// File containing the password for divera247, which is referenced in the config
+    #[arg(long, requires = "divera_username")]
+    pub divera_password_file: Option<String>,
+    /// Access key for divera247, used instead of username and password
+    #[arg(long, conflicts_with_all = ["divera_username", "divera_secret"])]
+    pub divera_access_key: Option<String>,
 
     /// Username for webdav server
-    #[arg(long)]
-    pub webdav_username: String,
+    #[arg(long, required_unless_present = "interactive")]
+    pub webdav_username: Option<String>,
     /// Password for webdav server
+    #[arg(long, required_unless_present_any = ["webdav_password_file", "interactive"])]
+    pub webdav_password: Option<String>,
+    /// File containing the password for webdav server, which is referenced in the config
     #[arg(long)]
-    pub webdav_password: String,
+    pub webdav_password_file: Option<String>,
     /// Root directory for webdav server
-    #[arg(long)]
-    pub webdav_directory: String,
+    #[arg(long, required_unless_present = "interactive")]
+    pub webdav_directory: Option<String>,
 }
 
 #[derive(Debug, Subcommand)]
 pub enum Report {
     /// Absences reports
-    Absences(PrintWriteUpload),
+    Absences(DatedPrintWriteUpload),
     /// Roster reports
     Roster(PrintWriteUpload),
     /// Station reports
     Station(PrintWriteUpload),
     /// Fire Operation reports
-    FireOperation(PrintWriteUpload),
+    FireOperation(FireOperationArguments),
+    /// Reports of any report type by its id
+    ById(ById),
+    /// All reports enabled in the config
+    All(All),
+    /// Number of absent and available members per day, by default for the next four weeks
+    Coverage(CoverageArguments),
+    /// Last exercise or operation under breathing apparatus of every wearer
+    Compliance(ComplianceArguments),
+}
+
+#[derive(Debug, Args)]
+pub struct CoverageArguments {
+    #[command(flatten)]
+    pub arguments: PrintWriteUpload,
+
+    #[command(flatten)]
+    pub filter: DateFilter,
+
+    /// Flags days with less available members [default: from config]
+    #[arg(long)]
+    pub minimum_strength: Option<usize>,
+}
+
+#[derive(Debug, Args)]
+pub struct ById {
+    /// Id of the report type (see report-types)
+    pub id: i64,
+
+    #[command(flatten)]
+    pub arguments: PrintWriteUpload,
+}
+
+#[derive(Debug, Args)]
+pub struct DatedPrintWriteUpload {
+    #[command(flatten)]
+    pub arguments: PrintWriteUpload,
+
+    #[command(flatten)]
+    pub filter: DateFilter,
+}
+
+#[derive(Debug, Args)]
+pub struct ComplianceArguments {
+    #[command(flatten)]
+    pub arguments: PrintWriteUpload,
+
+    /// Flags wearers whose deadline is within this number of days [default: from config]
+    #[arg(long)]
+    pub warning_days: Option<u64>,
+
+    /// Lists all members, including those without any report
+    #[arg(long)]
+    pub all_members: bool,
+
+    /// Reference date of the check (YYYY-MM-DD) [default: today]
+    #[arg(long)]
+    pub date: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct FireOperationArguments {
+    #[command(flatten)]
+    pub arguments: PrintWriteUpload,
+
+    #[command(flatten)]
+    pub filter: DateFilter,
+
+    /// Aggregates the reports per member and year as additional worksheet
+    #[arg(long)]
+    pub summary: bool,
+}
+
+#[derive(Default, Debug, Args)]
+pub struct DateFilter {
+    /// Only reports on or after this date (YYYY-MM-DD)
+    #[arg(long, conflicts_with_all = ["last_month", "year"])]
+    pub from: Option<String>,
+
+    /// Only reports on or before this date (YYYY-MM-DD)
+    #[arg(long, conflicts_with_all = ["last_month", "year"])]
+    pub to: Option<String>,
+
+    /// Only reports of the last month
+    #[arg(long, conflicts_with = "year")]
+    pub last_month: bool,
+
+    /// Only reports of the year
+    #[arg(long)]
+    pub year: Option<i32>,
+}
+
+#[derive(Debug, Args)]
+pub struct All {
+    #[command(flatten)]
+    pub output: PrintWriteUploadAll,
+
+    /// Combines all reports into a single xlsx file with one worksheet per report.
+    /// The file name may contain {date}, {year}, {month} and {day}
+    #[arg(long, value_name = "FILE_NAME", conflicts_with = "print")]
+    pub combined: Option<String>,
 }
 
 #[derive(Default, Debug, Args)]
 #[group(required = true, multiple = false)]
-pub struct PrintWriteUpload {
+pub struct PrintWriteUploadAll {
     /// Prints the reports in a table format
     #[arg(long)]
     pub print: bool,
 
-    /// Writes the reports to an xlsx file
-    #[arg(long)]
+    /// Writes the reports as xlsx files into a directory
+    #[arg(long, value_name = "DIRECTORY")]
     pub write: Option<String>,
 
-    /// Exports the report as xlsx and upload it to webdav server
+    /// Exports the reports as xlsx files and uploads them to their upload targets
     #[arg(long)]
+    pub upload: bool,
+}
+
+#[derive(Default, Debug, Args)]
+#[command(group(ArgGroup::new("output").required(true).multiple(false)))]
+pub struct PrintWriteUpload {
+    /// Prints the reports in a table format
+    #[arg(long, group = "output")]
+    pub print: bool,
+
+    /// Writes the reports to a file. The name may contain {title}, {date}, {year}, {month} and {day}
+    #[arg(long, group = "output")]
+    pub write: Option<String>,
+
+    /// Exports the reports and uploads them to their upload targets under the file name,
+    /// which may contain the placeholders of --write
+    #[arg(long, group = "output")]
     pub upload: Option<String>,
+
+    /// Format of the reports [default: table for print, xlsx otherwise]
+    #[arg(long, value_enum)]
+    pub format: Option<Format>,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Format {
+    Xlsx,
+    Csv,
+    Json,
+    /// Newline delimited json
+    Ndjson,
+    /// iCalendar (absences only)
+    Ics,
 }
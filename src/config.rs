@@ -1,59 +1,816 @@
-use std::{fs, path::Path};
+use std::{
+    collections::BTreeMap,
+    env,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    process::{Command, Stdio},
+    time::Duration,
+};
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
-use toml;
+
+use crate::{
+    divera,
+    reports::{absent, fire_operation, roster, station},
+    upload::{
+        directory::DirectoryTarget, fingerprints::Fingerprints, s3::S3Target, sftp::SftpTarget,
+        smtp::SmtpTarget, webdav::WebDavTarget, Destinations, UploadTarget,
+    },
+};
+
+/// Name of the upload target given by the webdav section
+pub const DEFAULT_TARGET: &str = "webdav";
+/// Key of the upload targets used for reports without their own targets
+const DEFAULT_TARGETS_KEY: &str = "default";
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
+#[non_exhaustive]
 pub struct Config {
     pub divera: Divera,
-    pub webdav: WebDav,
+    /// Default upload target, named `webdav` in the report targets
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webdav: Option<WebDav>,
+    /// Further upload targets by name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub targets: BTreeMap<String, Target>,
+    #[serde(default)]
+    pub reports: Reports,
+    #[serde(default)]
+    pub coverage: Coverage,
+    #[serde(default)]
+    pub compliance: Compliance,
+    #[serde(default)]
+    pub parsing: Parsing,
+    #[serde(default)]
+    pub mapping: Mappings,
+}
+
+/// Reports which are exported by `report all`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct Reports {
+    pub enabled: Vec<ReportKind>,
+    /// Ids of report types which are exported as generic reports
+    pub generic: Vec<i64>,
+    /// Names of the upload targets per report. Reports are keyed by their kind,
+    /// the id of generic report types, `coverage`, `compliance` or `combined`.
+    /// Reports without an entry use `default`, which falls back to the webdav section.
+    pub targets: BTreeMap<String, Vec<String>>,
+    /// File which keeps the fingerprints of the uploaded reports, unchanged reports are not uploaded again
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fingerprints: Option<String>,
+    /// Template of the file names of `report all`, see [`crate::reports::file_name::render`]
+    pub file_name: String,
+    /// Templates of the file names per report, keyed like the targets.
+    /// Defaults to the names of the former upload services, so published files keep their names.
+    #[serde(default = "default_file_names")]
+    pub file_names: BTreeMap<String, String>,
+    /// Keeps dated copies of the written and uploaded files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archive: Option<Archive>,
 }
 
+/// Dated copies of the files, e.g. `archive/Abwesenheiten_2025-10-18.xlsx`
 #[derive(Clone, Debug, Deserialize, Serialize)]
+#[non_exhaustive]
+pub struct Archive {
+    /// Subdirectory next to the files
+    #[serde(default = "default_archive_directory")]
+    pub directory: String,
+    /// Copies older than this number of days are removed, copies are kept forever without it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retention_days: Option<u64>,
+}
+
+/// Settings of the absence coverage analysis
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct Coverage {
+    /// Days with less available members are flagged
+    pub minimum_strength: usize,
+}
+
+/// Settings of the breathing apparatus compliance check
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct Compliance {
+    /// Wearers whose deadline is within this number of days are flagged
+    pub warning_days: u64,
+}
+
+/// Handling of reports which can not be parsed
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct Parsing {
+    /// Skip malformed reports instead of aborting
+    pub lenient: bool,
+    /// Number of skipped reports which is tolerated before failing
+    pub max_errors: usize,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReportKind {
+    Absences,
+    Roster,
+    Station,
+    FireOperation,
+}
+
+impl Reports {
+    /// Template of the file name of the reports with the key, see [`Reports::file_names`]
+    pub fn file_name(&self, key: &str) -> &str {
+        self.file_names.get(key).unwrap_or(&self.file_name)
+    }
+
+    /// Every report type may only be exported once, since the worksheets
+    /// of the combined workbook are named by the report type
+    fn check_report_types(&self, mapping: &Mappings) -> Result<()> {
+        let mut report_types = BTreeMap::new();
+        for kind in &self.enabled {
+            let id = mapping.get(*kind).report_type;
+            match report_types.insert(id, kind.key().to_string()) {
+                Some(key) if key == kind.key() => bail!("Report {key} is enabled more than once"),
+                Some(key) => bail!(
+                    "Report type {id} is exported as {key} and as {}",
+                    kind.key()
+                ),
+                None => {}
+            }
+        }
+        for id in &self.generic {
+            if let Some(key) = report_types.insert(*id, id.to_string()) {
+                bail!("Report type {id} is exported as {key} and as generic report");
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ReportKind {
+    /// Key of the report kind in the config
+    pub fn key(&self) -> &'static str {
+        match self {
+            ReportKind::Absences => "absences",
+            ReportKind::Roster => "roster",
+            ReportKind::Station => "station",
+            ReportKind::FireOperation => "fire-operation",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[non_exhaustive]
 pub struct Divera {
-    pub username: String,
-    pub password: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(flatten)]
+    pub password: Password,
+    /// Access key of a user or cluster, used instead of username and password
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access_key: Option<String>,
+    /// Base url of the divera api
+    #[serde(default = "default_divera_url")]
+    pub url: String,
+    /// User agent of the requests, defaults to the name and version of this tool
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    /// Url of a proxy for all requests to divera
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// File which keeps the access token between runs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_cache: Option<String>,
+    /// Connect timeout of the requests to divera and webdav targets
+    #[serde(default = "default_connect_timeout")]
+    pub connect_timeout_secs: u64,
+    /// Timeout of a whole request including reading the response, also used for webdav targets
+    #[serde(default = "default_timeout")]
+    pub timeout_secs: u64,
+    /// Retries of idempotent requests after transient failures
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    /// Delay before the first retry, doubled with every further retry
+    #[serde(default = "default_backoff")]
+    pub backoff_millis: u64,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
+#[non_exhaustive]
 pub struct WebDav {
     pub username: String,
-    pub password: String,
+    #[serde(flatten)]
+    pub password: Password,
     pub root_directory: String,
 }
 
+/// Destination of uploaded reports
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+#[non_exhaustive]
+pub enum Target {
+    Webdav(WebDav),
+    /// Local or mounted network directory
+    Directory(Directory),
+    /// Directory on a ssh server, uploaded with the sftp program
+    Sftp(Sftp),
+    /// Bucket of an S3 compatible object storage like MinIO
+    S3(S3),
+    /// Mail with the report as attachment
+    Smtp(Smtp),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[non_exhaustive]
+pub struct Directory {
+    pub path: String,
+}
+
+impl Directory {
+    pub fn new(path: String) -> Self {
+        Self { path }
+    }
+}
+
+/// Authentication is left to ssh, so a key without passphrase or an agent is required
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[non_exhaustive]
+pub struct Sftp {
+    pub host: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    /// Remote directory, relative to the home directory unless absolute
+    #[serde(default)]
+    pub directory: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identity_file: Option<String>,
+    /// Sftp program which is run in batch mode
+    #[serde(default = "default_sftp_program")]
+    pub program: String,
+}
+
+/// The secret access key is configured like a password
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[non_exhaustive]
+pub struct S3 {
+    /// Url of the storage, buckets are addressed by path
+    pub endpoint: String,
+    pub bucket: String,
+    #[serde(default = "default_s3_region")]
+    pub region: String,
+    pub access_key_id: String,
+    #[serde(flatten)]
+    pub secret_access_key: Password,
+    /// Prefix of the object keys, e.g. a directory like `reports/`
+    #[serde(default)]
+    pub prefix: String,
+}
+
+/// Mail server and recipients, the password is only used together with a username.
+/// Requires the fingerprints file of the reports, which tells the new entries of a report.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[non_exhaustive]
+pub struct Smtp {
+    pub host: String,
+    /// Port of the server [default: 587 for starttls, 465 for tls, 25 otherwise]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(flatten)]
+    pub password: Password,
+    pub from: String,
+    pub recipients: Vec<String>,
+    /// Subject of the mail, which may contain the placeholders of file names
+    #[serde(default = "default_smtp_subject")]
+    pub subject: String,
+}
+
+/// Encryption of the connection to the mail server
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SmtpSecurity {
+    #[default]
+    Starttls,
+    Tls,
+    /// Unencrypted, only for local mail servers
+    None,
+}
+
+/// Password given inline or read from a file, an environment variable or the output of a command.
+/// Exactly one source has to be configured.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Password {
+    #[serde(rename = "password", default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// File containing the password, e.g. a secret of agenix or sops
+    #[serde(
+        rename = "password_file",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub file: Option<String>,
+    /// Environment variable containing the password
+    #[serde(
+        rename = "password_env",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub env: Option<String>,
+    /// Shell command printing the password
+    #[serde(
+        rename = "password_command",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub command: Option<String>,
+}
+
+/// Method to authenticate at divera
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Authentication {
+    Password {
+        username: String,
+        password: Password,
+    },
+    AccessKey(String),
+}
+
+/// Binds the report structs to the fields and options of the divera report types.
+/// Missing sections, fields and options fall back to the defaults of the report.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(from = "MappingOverrides")]
+#[non_exhaustive]
+pub struct Mappings {
+    pub absences: Mapping,
+    pub fire_operation: Mapping,
+    pub roster: Mapping,
+    pub station: Mapping,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[non_exhaustive]
+pub struct Mapping {
+    /// Id of the divera report type
+    pub report_type: i64,
+    /// Report field name to divera field id
+    #[serde(default)]
+    pub fields: BTreeMap<String, String>,
+    /// Report field name to variant name to divera option id
+    #[serde(default)]
+    pub options: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// Mapping sections as written in the config, which only override parts of the defaults
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct MappingOverrides {
+    absences: Option<MappingOverride>,
+    fire_operation: Option<MappingOverride>,
+    roster: Option<MappingOverride>,
+    station: Option<MappingOverride>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MappingOverride {
+    report_type: Option<i64>,
+    #[serde(default)]
+    fields: BTreeMap<String, String>,
+    #[serde(default)]
+    options: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Mappings {
+    /// Mapping of the report kind
+    pub fn get(&self, kind: ReportKind) -> &Mapping {
+        match kind {
+            ReportKind::Absences => &self.absences,
+            ReportKind::Roster => &self.roster,
+            ReportKind::Station => &self.station,
+            ReportKind::FireOperation => &self.fire_operation,
+        }
+    }
+}
+
 impl Config {
     pub fn new(
-        divera_username: String,
-        divera_password: String,
+        authentication: Authentication,
         webdav_username: String,
-        webdav_password: String,
+        webdav_password: Password,
         webdav_directory: String,
     ) -> Self {
+        let (username, password, access_key) = match authentication {
+            Authentication::Password { username, password } => (Some(username), password, None),
+            Authentication::AccessKey(access_key) => (None, Password::default(), Some(access_key)),
+        };
         Config {
             divera: Divera {
-                username: divera_username,
-                password: divera_password,
+                username,
+                password,
+                access_key,
+                url: default_divera_url(),
+                user_agent: None,
+                proxy: None,
+                token_cache: None,
+                connect_timeout_secs: default_connect_timeout(),
+                timeout_secs: default_timeout(),
+                retries: default_retries(),
+                backoff_millis: default_backoff(),
             },
-            webdav: WebDav {
+            webdav: Some(WebDav {
                 username: webdav_username,
                 password: webdav_password,
                 root_directory: webdav_directory,
-            },
+            }),
+            targets: BTreeMap::default(),
+            reports: Reports::default(),
+            coverage: Coverage::default(),
+            compliance: Compliance::default(),
+            parsing: Parsing::default(),
+            mapping: Mappings::default(),
         }
     }
 
     pub fn read(path: &Path) -> Result<Self> {
         let config = fs::read_to_string(path).context("Failed to read config")?;
-        let config = toml::from_str(&config).context("Failed to parse config")?;
+        let config: Config = toml::from_str(&config).context("Failed to parse config")?;
+        config.divera.authentication()?;
+        for name in config.reports.targets.values().flatten() {
+            let target = config.target(name)?;
+            // New entries are found by comparing with the entries of the last upload
+            if matches!(target, Target::Smtp(_)) && config.reports.fingerprints.is_none() {
+                bail!("Smtp target \"{name}\" needs a fingerprints file in [reports] to find the new entries");
+            }
+        }
+        config.reports.check_report_types(&config.mapping)?;
         log::debug!("Read config: {:#?}", config);
         Ok(config)
     }
 
+    /// Writes the config only readable by the owner, since it may contain secrets
     pub fn write(&self, path: &Path) -> Result<()> {
         let config = toml::to_string(&self).context("Failed to render config")?;
-        fs::write(path, config).context("Failed to write config")?;
+        write_private(path, &config).context("Failed to write config")?;
         Ok(())
     }
+
+    /// Returns the upload targets of the reports with the key and the fingerprints of their uploads
+    pub fn destinations(&self, key: &str) -> Result<Destinations> {
+        Ok(Destinations {
+            targets: self.upload_targets(key)?,
+            fingerprints: self.reports.fingerprints.as_ref().map(Fingerprints::new),
+            archive: self.reports.archive.clone(),
+        })
+    }
+
+    /// Returns the upload targets of the reports with the key, see [`Reports::targets`]
+    pub fn upload_targets(&self, key: &str) -> Result<Vec<Box<dyn UploadTarget>>> {
+        let default = vec![DEFAULT_TARGET.to_string()];
+        let names = self
+            .reports
+            .targets
+            .get(key)
+            .or_else(|| self.reports.targets.get(DEFAULT_TARGETS_KEY))
+            .unwrap_or(&default);
+        names
+            .iter()
+            .map(|name| {
+                self.target(name)?
+                    .upload_target(name, &self.divera.settings())
+            })
+            .collect()
+    }
+
+    /// Returns the target with the name, the webdav section is named [`DEFAULT_TARGET`]
+    fn target(&self, name: &str) -> Result<Target> {
+        match (self.targets.get(name), &self.webdav) {
+            (Some(target), _) => Ok(target.clone()),
+            (None, Some(webdav)) if name == DEFAULT_TARGET => Ok(Target::Webdav(webdav.clone())),
+            (None, None) if name == DEFAULT_TARGET => {
+                bail!("No upload target configured, add a webdav section or targets")
+            }
+            _ => bail!("Unknown upload target \"{name}\""),
+        }
+    }
+}
+
+impl Target {
+    /// Http targets use the timeouts of the divera settings
+    pub fn upload_target(
+        self,
+        name: &str,
+        settings: &divera::Settings,
+    ) -> Result<Box<dyn UploadTarget>> {
+        Ok(match self {
+            Target::Webdav(config) => Box::new(WebDavTarget::new(
+                name,
+                config,
+                settings.connect_timeout,
+                settings.timeout,
+            )?),
+            Target::Directory(config) => Box::new(DirectoryTarget::new(name, config)),
+            Target::Sftp(config) => Box::new(SftpTarget::new(name, config)),
+            Target::S3(config) => Box::new(S3Target::new(name, config)),
+            Target::Smtp(config) => Box::new(SmtpTarget::new(name, config)),
+        })
+    }
+}
+
+impl Divera {
+    /// Returns the configured authentication, exactly one method has to be configured
+    pub fn authentication(&self) -> Result<Authentication> {
+        match (&self.username, self.password.is_set(), &self.access_key) {
+            (Some(username), true, None) => Ok(Authentication::Password {
+                username: username.clone(),
+                password: self.password.clone(),
+            }),
+            (None, false, Some(access_key)) => Ok(Authentication::AccessKey(access_key.clone())),
+            (_, _, Some(_)) => {
+                bail!("Configure either an access key or username and password for divera")
+            }
+            _ => bail!("Configure username and password or an access key for divera"),
+        }
+    }
+
+    pub fn settings(&self) -> divera::Settings {
+        divera::Settings {
+            user_agent: self
+                .user_agent
+                .clone()
+                .unwrap_or_else(|| divera::USER_AGENT.to_string()),
+            proxy: self.proxy.clone(),
+            connect_timeout: Duration::from_secs(self.connect_timeout_secs),
+            timeout: Duration::from_secs(self.timeout_secs),
+            retries: self.retries,
+            backoff: Duration::from_millis(self.backoff_millis),
+        }
+    }
+}
+
+impl Password {
+    pub fn value(password: String) -> Self {
+        Self {
+            value: Some(password),
+            ..Default::default()
+        }
+    }
+
+    pub fn file(path: String) -> Self {
+        Self {
+            file: Some(path),
+            ..Default::default()
+        }
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.sources() > 0
+    }
+
+    /// Reads the password from its source
+    pub fn resolve(&self) -> Result<String> {
+        if self.sources() > 1 {
+            bail!(
+                "Only one of password, password_file, password_env and password_command may be set"
+            );
+        }
+
+        if let Some(password) = &self.value {
+            Ok(password.clone())
+        } else if let Some(path) = &self.file {
+            let password = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read password file \"{path}\""))?;
+            Ok(trim_line_end(password))
+        } else if let Some(name) = &self.env {
+            env::var(name)
+                .with_context(|| format!("Failed to read environment variable \"{name}\""))
+        } else if let Some(command) = &self.command {
+            let output = Command::new("sh")
+                .arg("-c")
+                .arg(command)
+                .stderr(Stdio::inherit())
+                .output()
+                .with_context(|| format!("Failed to run password command \"{command}\""))?;
+            if !output.status.success() {
+                bail!(
+                    "Password command \"{command}\" failed with {}",
+                    output.status
+                );
+            }
+            let password =
+                String::from_utf8(output.stdout).context("Password command printed no utf-8")?;
+            Ok(trim_line_end(password))
+        } else {
+            bail!("No password configured")
+        }
+    }
+
+    fn sources(&self) -> usize {
+        [&self.value, &self.file, &self.env, &self.command]
+            .iter()
+            .filter(|source| source.is_some())
+            .count()
+    }
+}
+
+/// Writes a file which is only readable and writable by the owner
+pub(crate) fn write_private(path: &Path, content: &str) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // The mode only applies to new files
+        if path.exists() {
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    let mut file = options.open(path)?;
+    file.write_all(content.as_bytes())
+}
+
+/// Removes the trailing newline, which most editors and commands add
+fn trim_line_end(mut password: String) -> String {
+    let length = password.trim_end_matches(['\n', '\r']).len();
+    password.truncate(length);
+    password
+}
+
+fn default_divera_url() -> String {
+    divera::BASE_URL.to_string()
+}
+
+fn default_connect_timeout() -> u64 {
+    divera::Settings::default().connect_timeout.as_secs()
+}
+
+fn default_timeout() -> u64 {
+    divera::Settings::default().timeout.as_secs()
+}
+
+fn default_retries() -> u32 {
+    divera::Settings::default().retries
+}
+
+fn default_backoff() -> u64 {
+    divera::Settings::default().backoff.as_millis() as u64
+}
+
+fn default_file_names() -> BTreeMap<String, String> {
+    [
+        (ReportKind::Absences, "Abwesenheiten.xlsx"),
+        (ReportKind::Roster, "Vorschläge_Dienstplan.xlsx"),
+        (
+            ReportKind::Station,
+            "Verbesserungsvorschläge_Feuerwehrhaus.xlsx",
+        ),
+        (ReportKind::FireOperation, "Atemschutz_Kurzbericht.xlsx"),
+    ]
+    .into_iter()
+    .map(|(kind, file_name)| (kind.key().to_string(), file_name.to_string()))
+    .collect()
+}
+
+fn default_archive_directory() -> String {
+    "archive".to_string()
+}
+
+fn default_sftp_program() -> String {
+    "sftp".to_string()
+}
+
+fn default_s3_region() -> String {
+    "us-east-1".to_string()
+}
+
+fn default_smtp_subject() -> String {
+    "{title}".to_string()
+}
+
+impl Default for Reports {
+    fn default() -> Self {
+        Self {
+            enabled: vec![
+                ReportKind::Absences,
+                ReportKind::Roster,
+                ReportKind::Station,
+                ReportKind::FireOperation,
+            ],
+            generic: Vec::default(),
+            targets: BTreeMap::default(),
+            fingerprints: None,
+            file_name: "{title}.xlsx".to_string(),
+            file_names: default_file_names(),
+            archive: None,
+        }
+    }
+}
+
+impl Default for Compliance {
+    fn default() -> Self {
+        Self { warning_days: 60 }
+    }
+}
+
+impl Default for Mappings {
+    fn default() -> Self {
+        Self {
+            absences: absent::default_mapping(),
+            fire_operation: fire_operation::default_mapping(),
+            roster: roster::default_mapping(),
+            station: station::default_mapping(),
+        }
+    }
+}
+
+impl From<MappingOverrides> for Mappings {
+    fn from(overrides: MappingOverrides) -> Self {
+        let defaults = Mappings::default();
+        Self {
+            absences: defaults.absences.merge(overrides.absences),
+            fire_operation: defaults.fire_operation.merge(overrides.fire_operation),
+            roster: defaults.roster.merge(overrides.roster),
+            station: defaults.station.merge(overrides.station),
+        }
+    }
+}
+
+impl Mapping {
+    /// Mapping of a generic report type, whose columns are built from the report type
+    pub fn generic(report_type: i64) -> Self {
+        Self {
+            report_type,
+            ..Default::default()
+        }
+    }
+
+    pub fn new(
+        report_type: i64,
+        fields: &[(&str, &str)],
+        options: &[(&str, &[(&str, &str)])],
+    ) -> Self {
+        let fields = fields
+            .iter()
+            .map(|(name, id)| (name.to_string(), id.to_string()))
+            .collect();
+        let options = options
+            .iter()
+            .map(|(name, variants)| {
+                let variants = variants
+                    .iter()
+                    .map(|(variant, id)| (variant.to_string(), id.to_string()))
+                    .collect();
+                (name.to_string(), variants)
+            })
+            .collect();
+
+        Self {
+            report_type,
+            fields,
+            options,
+        }
+    }
+
+    /// Replaces the report type, fields and option variants which are given by the override
+    fn merge(mut self, mapping: Option<MappingOverride>) -> Self {
+        let Some(mapping) = mapping else {
+            return self;
+        };
+        if let Some(report_type) = mapping.report_type {
+            self.report_type = report_type;
+        }
+        self.fields.extend(mapping.fields);
+        for (name, variants) in mapping.options {
+            self.options.entry(name).or_default().extend(variants);
+        }
+        self
+    }
+
+    /// Returns the name of the report field which is bound to the divera field id
+    pub fn field(&self, id: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(_, field_id)| field_id.as_str() == id)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Returns the name of the variant of the report field which is bound to the divera option id
+    pub fn option(&self, field: &str, id: &str) -> Option<&str> {
+        self.options
+            .get(field)?
+            .iter()
+            .find(|(_, option_id)| option_id.as_str() == id)
+            .map(|(variant, _)| variant.as_str())
+    }
 }
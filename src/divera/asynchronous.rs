@@ -0,0 +1,135 @@
+use futures_util::future::try_join_all;
+use reqwest::{Client, RequestBuilder, Url};
+use serde::de::DeserializeOwned;
+
+use super::{
+    cached_token_accepted, configure, handle_response, join_url, login_error, login_request,
+    next_retry, parse_base_url, schema::response, store_token, DiveraError, Received, Result,
+    Settings, TokenCache, ENDPOINT_JWT, ENDPOINT_LOGIN, ENDPOINT_PULL_ALL, ENDPOINT_REPORTS,
+    ENDPOINT_REPORTTYPES,
+};
+
+/// Async variant of the [`super::DiveraClient`] for services running on tokio
+#[derive(Clone, Debug)]
+pub struct AsyncDiveraClient {
+    base_url: Url,
+    access_token: Option<String>,
+    http: Client,
+    settings: Settings,
+}
+
+impl AsyncDiveraClient {
+    pub fn new(base_url: &str, settings: Settings) -> Result<Self> {
+        let base_url = parse_base_url(base_url)?;
+        let http = configure(Client::builder(), &settings)?.build()?;
+
+        Ok(Self {
+            base_url,
+            access_token: None,
+            http,
+            settings,
+        })
+    }
+
+    /// Uses an existing access token instead of logging in
+    pub fn with_access_token(mut self, access_token: &str) -> Self {
+        self.access_token = Some(access_token.to_string());
+        self
+    }
+
+    /// Logs in and keeps the access token for the following requests
+    pub async fn login(&mut self, username: &str, password: &str) -> Result<response::Login> {
+        let url = join_url(&self.base_url, ENDPOINT_LOGIN)?;
+        let request = self.http.post(url).json(&login_request(username, password));
+        let response = receive(request).await?;
+
+        let login: response::Login = handle_response(response).map_err(login_error)?;
+        self.access_token = Some(login.user.access_token.clone());
+        Ok(login)
+    }
+
+    /// Reuses the cached access token while divera accepts it.
+    /// Otherwise logs in and caches the new token.
+    pub async fn login_cached(
+        &mut self,
+        username: &str,
+        password: &str,
+        cache: &TokenCache,
+    ) -> Result<()> {
+        let url = self.base_url.to_string();
+        if let Some(access_token) = cache.load(&url, username) {
+            self.access_token = Some(access_token);
+            if cached_token_accepted(self.jwt().await)? {
+                return Ok(());
+            }
+        }
+
+        let login = self.login(username, password).await?;
+        store_token(cache, &url, username, &login.user.access_token);
+        Ok(())
+    }
+
+    pub async fn jwt(&self) -> Result<response::Jwt> {
+        self.get(ENDPOINT_JWT, &[]).await
+    }
+
+    pub async fn report_types(&self) -> Result<response::ReportTypes> {
+        self.get(ENDPOINT_REPORTTYPES, &[]).await
+    }
+
+    pub async fn reports(&self, report_type: i64) -> Result<response::Reports> {
+        self.get(ENDPOINT_REPORTS, &[("id", &report_type.to_string())])
+            .await
+    }
+
+    /// Fetches the reports of several report types concurrently, in the order of the ids
+    pub async fn reports_of(&self, report_types: &[i64]) -> Result<Vec<response::Reports>> {
+        try_join_all(report_types.iter().map(|id| self.reports(*id))).await
+    }
+
+    pub async fn pull_all(&self) -> Result<response::All> {
+        self.get(ENDPOINT_PULL_ALL, &[]).await
+    }
+
+    /// Sends an authenticated get request, which is retried after transient failures
+    async fn get<T: DeserializeOwned>(&self, endpoint: &str, query: &[(&str, &str)]) -> Result<T> {
+        let access_token = self
+            .access_token
+            .as_deref()
+            .ok_or(DiveraError::MissingAccessToken)?;
+        let request = self
+            .http
+            .get(join_url(&self.base_url, endpoint)?)
+            .query(&[("accesskey", access_token)])
+            .query(query);
+        let response = send_with_retries(request, &self.settings).await?;
+
+        handle_response(response)
+    }
+}
+
+async fn send_with_retries(request: RequestBuilder, settings: &Settings) -> Result<Received> {
+    let mut attempt = 0;
+    loop {
+        let Some(current) = request.try_clone() else {
+            return receive(request).await;
+        };
+        let result = receive(current).await;
+        let Some(delay) = next_retry(&result, attempt, settings) else {
+            return result;
+        };
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+async fn receive(request: RequestBuilder) -> Result<Received> {
+    let response = request.send().await?;
+    log::debug!("Response headers: {:#?}", &response);
+
+    Ok(Received {
+        status: response.status(),
+        headers: response.headers().clone(),
+        text: response.text().await?,
+    })
+}
@@ -0,0 +1,75 @@
+use reqwest::StatusCode;
+use serde_json::Value;
+use thiserror::Error;
+
+/// Errors of the divera client
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum DiveraError {
+    #[error("Invalid divera url \"{url}\"")]
+    Url {
+        url: String,
+        #[source]
+        source: url::ParseError,
+    },
+    #[error("Failed to connect to divera")]
+    Network(#[from] reqwest::Error),
+    #[error("Not logged in to divera")]
+    MissingAccessToken,
+    #[error("Authentication failed: {}. Check the divera credentials in the config", .0.join(", "))]
+    Authentication(Vec<String>),
+    #[error("Divera responded with {status}")]
+    Status { status: StatusCode, body: String },
+    #[error("Divera reported errors: {}", .0.join(", "))]
+    Api(Vec<String>),
+    #[error("Failed to parse divera response into {type_name} at \"{path}\"")]
+    Deserialize {
+        type_name: &'static str,
+        path: String,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+impl DiveraError {
+    /// Creates an api error from the `errors` value of an unsuccessful response
+    pub fn api(errors: Option<&Value>) -> Self {
+        let mut messages = Vec::default();
+        if let Some(errors) = errors {
+            collect_messages(errors, &mut messages);
+        }
+        if messages.is_empty() {
+            messages.push("Unknown error".to_string());
+        }
+        Self::Api(messages)
+    }
+
+    /// Whether retrying the request later might succeed
+    pub fn is_transient(&self) -> bool {
+        match self {
+            // A body which was interrupted while it was read fails to be decoded
+            DiveraError::Network(error) => {
+                error.is_timeout() || error.is_connect() || error.is_body() || error.is_decode()
+            }
+            DiveraError::Status { status, .. } => {
+                *status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Divera returns the errors as string, list or map of field names to messages
+fn collect_messages(errors: &Value, messages: &mut Vec<String>) {
+    match errors {
+        Value::String(message) => messages.push(message.clone()),
+        Value::Array(errors) => errors
+            .iter()
+            .for_each(|error| collect_messages(error, messages)),
+        Value::Object(errors) => errors
+            .values()
+            .for_each(|error| collect_messages(error, messages)),
+        Value::Null => {}
+        error => messages.push(error.to_string()),
+    }
+}
@@ -1,15 +1,19 @@
-#![allow(unused)]
-
+#[cfg(feature = "async")]
+pub mod asynchronous;
+pub mod error;
 pub mod schema;
+pub mod token_cache;
 
-use std::any::type_name;
+use std::{any::type_name, thread, time::Duration};
 
-use anyhow::{bail, Context, Result};
+#[cfg(feature = "async")]
+pub use asynchronous::AsyncDiveraClient;
+pub use error::DiveraError;
 use reqwest::{
     self,
-    blocking::{RequestBuilder, Response},
-    header::COOKIE,
-    Url,
+    blocking::{Client, RequestBuilder},
+    header::{HeaderMap, RETRY_AFTER},
+    Proxy, StatusCode, Url,
 };
 use schema::{
     request,
@@ -17,120 +21,396 @@ use schema::{
 };
 use serde::de::DeserializeOwned;
 use serde_json::Value;
+pub use token_cache::TokenCache;
 
-const BASE_URL: &str = "https://app.divera247.com/api/";
+pub const BASE_URL: &str = "https://app.divera247.com/api/";
+pub const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
 const ENDPOINT_PULL_ALL: &str = "v2/pull/all";
 const ENDPOINT_LOGIN: &str = "v2/auth/login";
 const ENDPOINT_JWT: &str = "v2/auth/jwt";
 const ENDPOINT_REPORTTYPES: &str = "v2/reporttypes";
 const ENDPOINT_REPORTS: &str = "v2/reporttypes/reports";
-const ENDPOINT_EXPORT_USERS: &str = "v2/management/export-users";
-const ENDPOINT_USERS: &str = "users";
+/// Upper bound of a delay requested by the `Retry-After` header
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+pub type Result<T> = std::result::Result<T, DiveraError>;
+
+/// Connection, timeouts and retries of the requests to divera
+#[derive(Clone, Debug)]
+pub struct Settings {
+    pub user_agent: String,
+    /// Url of a proxy for all requests
+    pub proxy: Option<String>,
+    pub connect_timeout: Duration,
+    /// Timeout of a whole request including reading the response
+    pub timeout: Duration,
+    /// Number of retries of idempotent requests after a transient failure
+    pub retries: u32,
+    /// Delay before the first retry, doubled with every further retry
+    pub backoff: Duration,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            user_agent: USER_AGENT.to_string(),
+            proxy: None,
+            connect_timeout: Duration::from_secs(10),
+            timeout: Duration::from_secs(30),
+            retries: 3,
+            backoff: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Client of the divera api which keeps the connection pool and the access token
+#[derive(Clone, Debug)]
+pub struct DiveraClient {
+    base_url: Url,
+    access_token: Option<String>,
+    http: Client,
+    settings: Settings,
+}
+
+impl DiveraClient {
+    pub fn new(base_url: &str, settings: Settings) -> Result<Self> {
+        let base_url = parse_base_url(base_url)?;
+        let http = configure(Client::builder(), &settings)?.build()?;
 
-pub fn login(username: &str, password: &str) -> Result<response::Login> {
-    let url = create_url(BASE_URL, ENDPOINT_LOGIN);
-    let body = request::LoginRequest {
+        Ok(Self {
+            base_url,
+            access_token: None,
+            http,
+            settings,
+        })
+    }
+
+    /// Uses an existing access token instead of logging in
+    pub fn with_access_token(mut self, access_token: &str) -> Self {
+        self.access_token = Some(access_token.to_string());
+        self
+    }
+
+    /// Logs in and keeps the access token for the following requests
+    pub fn login(&mut self, username: &str, password: &str) -> Result<response::Login> {
+        let url = join_url(&self.base_url, ENDPOINT_LOGIN)?;
+        let request = self.http.post(url).json(&login_request(username, password));
+        let response = receive(request)?;
+
+        let login: response::Login = handle_response(response).map_err(login_error)?;
+        self.access_token = Some(login.user.access_token.clone());
+        Ok(login)
+    }
+
+    /// Reuses the cached access token while divera accepts it.
+    /// Otherwise logs in and caches the new token.
+    pub fn login_cached(
+        &mut self,
+        username: &str,
+        password: &str,
+        cache: &TokenCache,
+    ) -> Result<()> {
+        let url = self.base_url.to_string();
+        if let Some(access_token) = cache.load(&url, username) {
+            self.access_token = Some(access_token);
+            if cached_token_accepted(self.jwt())? {
+                return Ok(());
+            }
+        }
+
+        let login = self.login(username, password)?;
+        store_token(cache, &url, username, &login.user.access_token);
+        Ok(())
+    }
+
+    pub fn jwt(&self) -> Result<response::Jwt> {
+        self.get(ENDPOINT_JWT, &[])
+    }
+
+    pub fn report_types(&self) -> Result<response::ReportTypes> {
+        self.get(ENDPOINT_REPORTTYPES, &[])
+    }
+
+    pub fn reports(&self, report_type: i64) -> Result<response::Reports> {
+        self.get(ENDPOINT_REPORTS, &[("id", &report_type.to_string())])
+    }
+
+    pub fn pull_all(&self) -> Result<response::All> {
+        self.get(ENDPOINT_PULL_ALL, &[])
+    }
+
+    // pub fn _export_users(&self, jwt: &str) -> Result<response::User> {
+    //     let url = join_url(&self.base_url, ENDPOINT_EXPORT_USERS)?;
+    //     let request = self.http.get(url).header(COOKIE, format!("_jwt={jwt}"));
+    //     let response = send(request)?;
+
+    //     handle_response(response)
+    // }
+
+    // pub fn _users(&self) -> Result<response::User> {
+    //     self.get(ENDPOINT_USERS, &[])
+    // }
+
+    /// Sends an authenticated get request, which is retried after transient failures
+    fn get<T: DeserializeOwned>(&self, endpoint: &str, query: &[(&str, &str)]) -> Result<T> {
+        let access_token = self
+            .access_token
+            .as_deref()
+            .ok_or(DiveraError::MissingAccessToken)?;
+        let request = self
+            .http
+            .get(join_url(&self.base_url, endpoint)?)
+            .query(&[("accesskey", access_token)])
+            .query(query);
+        let response = send_with_retries(request, &self.settings)?;
+
+        handle_response(response)
+    }
+}
+
+fn parse_base_url(base_url: &str) -> Result<Url> {
+    // Without a trailing slash the last path segment of the base would be replaced
+    if base_url.ends_with('/') {
+        Url::parse(base_url)
+    } else {
+        Url::parse(&format!("{base_url}/"))
+    }
+    .map_err(|source| DiveraError::Url {
+        url: base_url.to_string(),
+        source,
+    })
+}
+
+fn join_url(base_url: &Url, endpoint: &str) -> Result<Url> {
+    base_url.join(endpoint).map_err(|source| DiveraError::Url {
+        url: format!("{base_url}{endpoint}"),
+        source,
+    })
+}
+
+fn login_request(username: &str, password: &str) -> request::LoginRequest {
+    request::LoginRequest {
         login: request::Login {
             username: username.to_string(),
             password: password.to_string(),
             jwt: false,
         },
-    };
-    let request = reqwest::blocking::Client::new().post(url).json(&body);
-    let response = send(request)?;
+    }
+}
 
-    let login = handle_response(response).with_context(|| "Failed to handle login response")?;
-    Ok(login)
+/// Applies the timeouts, user agent and proxy of the settings to a blocking or async client
+fn configure<B: ClientBuilder>(builder: B, settings: &Settings) -> Result<B> {
+    let builder = builder
+        .connect_timeout(settings.connect_timeout)
+        .timeout(settings.timeout)
+        .user_agent(&settings.user_agent);
+    Ok(match &settings.proxy {
+        Some(proxy) => builder.proxy(Proxy::all(proxy)?),
+        None => builder,
+    })
 }
 
-pub fn _jwt(access_token: &str) -> Result<response::Jwt> {
-    let url = create_url(BASE_URL, ENDPOINT_JWT);
-    let request = reqwest::blocking::Client::new()
-        .get(url)
-        .query(&[("accesskey", access_token)]);
-    let response = send(request)?;
+/// Common settings of the blocking and async client builders of reqwest
+trait ClientBuilder: Sized {
+    fn connect_timeout(self, timeout: Duration) -> Self;
+    fn timeout(self, timeout: Duration) -> Self;
+    fn user_agent(self, user_agent: &str) -> Self;
+    fn proxy(self, proxy: Proxy) -> Self;
+}
 
-    let jwt = handle_response(response).with_context(|| "Failed to handle login response")?;
-    Ok(jwt)
+macro_rules! impl_client_builder {
+    ($builder:ty) => {
+        impl ClientBuilder for $builder {
+            fn connect_timeout(self, timeout: Duration) -> Self {
+                self.connect_timeout(timeout)
+            }
+            fn timeout(self, timeout: Duration) -> Self {
+                self.timeout(timeout)
+            }
+            fn user_agent(self, user_agent: &str) -> Self {
+                self.user_agent(user_agent)
+            }
+            fn proxy(self, proxy: Proxy) -> Self {
+                self.proxy(proxy)
+            }
+        }
+    };
 }
 
-pub fn report_types(access_token: &str) -> Result<response::ReportTypes> {
-    let url = create_url(BASE_URL, ENDPOINT_REPORTTYPES);
-    let request = reqwest::blocking::Client::new()
-        .get(url)
-        .query(&[("accesskey", access_token)]);
-    let response = send(request)?;
+impl_client_builder!(reqwest::blocking::ClientBuilder);
+#[cfg(feature = "async")]
+impl_client_builder!(reqwest::ClientBuilder);
 
-    Ok(handle_response(response).with_context(|| "Failed to handle report-types response")?)
+/// Whether divera accepted the cached access token, judged by the result of a request with it.
+/// Errors other than a rejected token are returned.
+fn cached_token_accepted<T>(result: Result<T>) -> Result<bool> {
+    match result {
+        Ok(_) => {
+            log::debug!("Reusing cached access token");
+            Ok(true)
+        }
+        Err(DiveraError::Authentication(_) | DiveraError::Api(_)) => {
+            log::info!("Cached access token was rejected, logging in");
+            Ok(false)
+        }
+        Err(error) => Err(error),
+    }
 }
 
-pub fn reports(access_token: &str, report_type: i64) -> Result<response::Reports> {
-    let url = create_url(BASE_URL, ENDPOINT_REPORTS);
-    let request = reqwest::blocking::Client::new().get(url).query(&[
-        ("accesskey", access_token),
-        ("id", &report_type.to_string()),
-    ]);
-    let response = send(request)?;
-
-    Ok(handle_response(response).with_context(|| "Failed to handle reports response")?)
+/// Caches the access token of a login, a cache which can not be written is only logged
+fn store_token(cache: &TokenCache, url: &str, username: &str, access_token: &str) {
+    if let Err(error) = cache.store(url, username, access_token) {
+        log::warn!(
+            "Failed to write token cache {}: {error}",
+            cache.path().display()
+        );
+    }
 }
 
-pub fn pull_all(access_token: &str) -> Result<response::All> {
-    let url = create_url(BASE_URL, ENDPOINT_PULL_ALL);
-    let request = reqwest::blocking::Client::new()
-        .get(url)
-        .query(&[("accesskey", access_token)]);
-    let response = send(request)?;
+/// Errors reported by divera at login are rejected credentials
+fn login_error(error: DiveraError) -> DiveraError {
+    match error {
+        DiveraError::Api(messages) => DiveraError::Authentication(messages),
+        error => error,
+    }
+}
 
-    Ok(handle_response(response).with_context(|| "Failed to handle pull all response")?)
+/// Response of divera whose body was read completely
+struct Received {
+    status: StatusCode,
+    headers: HeaderMap,
+    text: String,
 }
 
-// pub fn _export_users(jwt: &str) -> Result<response::User> {
-//     let url = create_url(BASE_URL, ENDPOINT_EXPORT_USERS);
-//     let request = reqwest::blocking::Client::new()
-//         .get(url)
-//         .header(COOKIE, format!("_jwt={jwt}"));
-//     let response = send(request)?;
+/// Sends the request and reads the response, so an interrupted body fails like the request
+fn receive(request: RequestBuilder) -> Result<Received> {
+    let response = request.send()?;
+    log::debug!("Response headers: {:#?}", &response);
 
-//     Ok(handle_response(response).with_context(|| "Failed to handle export-users response")?)
-// }
+    Ok(Received {
+        status: response.status(),
+        headers: response.headers().clone(),
+        text: response.text()?,
+    })
+}
 
-// pub fn _users(access_token: &str) -> Result<response::User> {
-//     let url = create_url(BASE_URL, ENDPOINT_USERS);
-//     let request = reqwest::blocking::Client::new()
-//         .get(url)
-//         .query(&[("accesskey", access_token)]);
-//     let response = send(request)?;
+/// Sends an idempotent request and retries it after transient failures
+/// with exponential backoff or the delay requested by the server
+fn send_with_retries(request: RequestBuilder, settings: &Settings) -> Result<Received> {
+    let mut attempt = 0;
+    loop {
+        let Some(current) = request.try_clone() else {
+            return receive(request);
+        };
+        let result = receive(current);
+        let Some(delay) = next_retry(&result, attempt, settings) else {
+            return result;
+        };
+        thread::sleep(delay);
+        attempt += 1;
+    }
+}
 
-//     Ok(handle_response(response).with_context(|| "Failed to handle users response")?)
-// }
+/// Delay before the next attempt of a request with the result.
+/// None if the request should not be retried.
+fn next_retry(result: &Result<Received>, attempt: u32, settings: &Settings) -> Option<Duration> {
+    if attempt >= settings.retries {
+        return None;
+    }
+    match result {
+        Ok(response) => retry_delay(response.status, &response.headers, attempt, settings),
+        Err(error) => error_retry_delay(error, attempt, settings),
+    }
+}
 
-fn create_url(base: &str, endpoint: &str) -> Url {
-    Url::parse(base).unwrap().join(endpoint).unwrap()
+/// Delay before retrying a request which was answered with the status.
+/// None if the request should not be retried.
+fn retry_delay(
+    status: StatusCode,
+    headers: &HeaderMap,
+    attempt: u32,
+    settings: &Settings,
+) -> Option<Duration> {
+    if status != StatusCode::TOO_MANY_REQUESTS && !status.is_server_error() {
+        return None;
+    }
+    let delay = retry_after(status, headers).unwrap_or_else(|| backoff(settings.backoff, attempt));
+    log::warn!("Divera responded with {status}, retrying in {delay:?}");
+    Some(delay)
 }
 
-fn send(request: RequestBuilder) -> Result<Response> {
-    let response = request.send()?;
-    log::debug!("Response headers: {:#?}", &response);
+/// Delay before retrying a request which failed with the error.
+/// None if the request should not be retried.
+fn error_retry_delay(error: &DiveraError, attempt: u32, settings: &Settings) -> Option<Duration> {
+    if !error.is_transient() {
+        return None;
+    }
+    let delay = backoff(settings.backoff, attempt);
+    log::warn!("{error}, retrying in {delay:?}");
+    Some(delay)
+}
 
-    Ok(response)
+/// Exponential backoff with a random jitter of up to half the delay
+fn backoff(base: Duration, attempt: u32) -> Duration {
+    let delay = base.saturating_mul(2u32.saturating_pow(attempt));
+    let jitter = fastrand::u64(0..=delay.as_millis() as u64 / 2);
+    delay.saturating_sub(Duration::from_millis(jitter))
 }
 
-fn handle_response<T: DeserializeOwned>(response: Response) -> Result<T> {
-    if !response.status().is_success() {
-        bail!("Request failed with {}", response.status());
+/// Delay requested by a 429 or 503 response, in seconds or as http date
+fn retry_after(status: StatusCode, headers: &HeaderMap) -> Option<Duration> {
+    if !matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+    ) {
+        return None;
     }
 
-    let response_text = response.text()?;
-    let raw_response: Value =
-        serde_json::from_str(&response_text).with_context(|| "Failed to parse JSON")?;
-    log::debug!("Response text: {:#?}", raw_response);
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    let delay = match value.parse::<u64>() {
+        Ok(seconds) => Duration::from_secs(seconds),
+        Err(_) => {
+            let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+            (date.to_utc() - chrono::Utc::now())
+                .to_std()
+                .unwrap_or_default()
+        }
+    };
+    Some(delay.min(MAX_RETRY_AFTER))
+}
+
+fn handle_response<T: DeserializeOwned>(response: Received) -> Result<T> {
+    parse_response(response.status, response.text)
+}
 
-    if !raw_response.get("success").unwrap().as_bool().unwrap() {
-        bail!("Request failed {}", raw_response.get("errors").unwrap());
+fn parse_response<T: DeserializeOwned>(status: StatusCode, response_text: String) -> Result<T> {
+    log::debug!("Response text: {response_text}");
+
+    if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+        return Err(DiveraError::Authentication(vec![status.to_string()]));
+    }
+    if !status.is_success() {
+        return Err(DiveraError::Status {
+            status,
+            body: response_text,
+        });
     }
 
-    let response: SuccessResponse<T> = serde_json::from_str(&response_text)
-        .with_context(|| format!("Failed to parse JSON to {}", type_name::<T>()))?;
+    let raw_response: Value = deserialize(&response_text)?;
+    if raw_response.get("success").and_then(Value::as_bool) != Some(true) {
+        return Err(DiveraError::api(raw_response.get("errors")));
+    }
+
+    let response: SuccessResponse<T> = deserialize(&response_text)?;
     Ok(response.data)
 }
+
+/// Deserializes the text and keeps the path of the failing field
+fn deserialize<T: DeserializeOwned>(text: &str) -> Result<T> {
+    let deserializer = &mut serde_json::Deserializer::from_str(text);
+    serde_path_to_error::deserialize(deserializer).map_err(|error| DiveraError::Deserialize {
+        type_name: type_name::<T>(),
+        path: error.path().to_string(),
+        source: error.into_inner(),
+    })
+}
@@ -1,6 +1,6 @@
-#![allow(unused)]
+use std::fmt::Display;
 
-use std::collections::HashMap;
+use comfy_table::{ContentArrangement, Table};
 
 pub mod request {
     use serde::Serialize;
@@ -163,3 +163,20 @@ pub mod response {
     //     pub receive_sms: bool,
     // }
 }
+
+impl Display for response::ReportTypes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut table = Table::new();
+        table
+            .set_header(vec!["ID", "Name", "Description"])
+            .set_content_arrangement(ContentArrangement::Dynamic);
+        for (id, item) in self.items.iter() {
+            table.add_row(vec![
+                id.to_string(),
+                item.name.clone().lines().collect(),
+                item.description.clone().lines().collect(),
+            ]);
+        }
+        f.write_str(&table.to_string())
+    }
+}
@@ -0,0 +1,70 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::write_private;
+
+/// File which keeps the access token of the last login between runs.
+/// It is only readable by the owner, since the token grants access to the account.
+#[derive(Clone, Debug)]
+pub struct TokenCache {
+    path: PathBuf,
+}
+
+/// Cached token together with the account it belongs to
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct Entry {
+    url: String,
+    username: String,
+    access_token: String,
+}
+
+impl TokenCache {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the cached token of the account, if there is one
+    pub fn load(&self, url: &str, username: &str) -> Option<String> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return None,
+            Err(error) => {
+                log::warn!(
+                    "Failed to read token cache {}: {error}",
+                    self.path.display()
+                );
+                return None;
+            }
+        };
+        let entry: Entry = match serde_json::from_str(&content) {
+            Ok(entry) => entry,
+            Err(error) => {
+                log::warn!(
+                    "Ignoring invalid token cache {}: {error}",
+                    self.path.display()
+                );
+                return None;
+            }
+        };
+
+        (entry.url == url && entry.username == username).then_some(entry.access_token)
+    }
+
+    pub fn store(&self, url: &str, username: &str, access_token: &str) -> io::Result<()> {
+        let entry = Entry {
+            url: url.to_string(),
+            username: username.to_string(),
+            access_token: access_token.to_string(),
+        };
+        let content = serde_json::to_string(&entry)?;
+        write_private(&self.path, &content)
+    }
+}
@@ -0,0 +1,32 @@
+//! Fetches reports from divera247 and exports them as xlsx, csv, json or iCalendar.
+//!
+//! The [`DiveraClient`] fetches the raw reports, which are parsed into the
+//! report models like [`AbsentReport`] and written with [`Export`] or
+//! published to the targets of [`upload`].
+//!
+//! The re-exports of the crate root are its stable interface. The types of
+//! [`config`] mirror the config file and are `#[non_exhaustive]`, so they are
+//! read with [`Config::read`] instead of being built field by field.
+
+pub mod config;
+pub mod divera;
+pub mod reports;
+pub mod upload;
+
+pub use config::Config;
+#[cfg(feature = "async")]
+pub use divera::AsyncDiveraClient;
+pub use divera::{
+    schema::response::{Consumer, Report, ReportTypesItem},
+    DiveraClient, DiveraError, Settings, TokenCache,
+};
+pub use reports::{
+    absent::AbsentReport,
+    compliance::Compliance,
+    coverage::Coverage,
+    fire_operation::{FireOperationReport, FireOperationSummary},
+    generic::GenericReports,
+    roster::RosterReport,
+    station::StationReport,
+    Export, OutputFormat, Parsed, ReportError, Reports,
+};
@@ -1,32 +1,97 @@
 mod cli;
-mod config;
-mod divera;
-mod reports;
+mod prompt;
 
-use anyhow::{bail, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{Days, Local, NaiveDate};
 use clap::Parser;
-use comfy_table::{ContentArrangement, Table};
-use config::Config;
-use divera::schema::response::{Consumer, ReportTypes};
-use env_logger;
-use reports::{
-    absent::AbsentReport, fire_operation::FireOperationReport, roster::RosterReport,
-    station::StationReport, Reports,
+use divera_reports::{
+    config::{
+        self, Authentication, Config, Directory, Mapping, Password, ReportKind, DEFAULT_TARGET,
+    },
+    divera::{
+        schema::response::{Consumer, ReportTypes},
+        DiveraClient, DiveraError, TokenCache, BASE_URL,
+    },
+    reports::{
+        absent::AbsentReport,
+        compliance::Compliance,
+        coverage::Coverage,
+        file_name,
+        fire_operation::{FireOperationReport, FireOperationSummary},
+        generic::GenericReports,
+        roster::RosterReport,
+        station::StationReport,
+        DateRange, Dated, Export, OutputFormat, Parsed, Reports,
+    },
+    upload::{
+        archive, directory::DirectoryTarget, fingerprints::fingerprint, webdav::WebDavTarget,
+        Summary,
+    },
 };
-use std::{collections::HashMap, fmt::Display, path::Path};
+use rust_xlsxwriter::Workbook;
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+use tempfile::tempdir;
 
 use cli::{Cli, Commands, PrintWriteUpload};
 
-const REPORT_ID_ABSENCES: i64 = 10538;
-const REPORT_ID_STATION: i64 = 14307;
-const REPORT_ID_ROSTER: i64 = 12112;
-const REPORT_ID_FIRE_OPERATION: i64 = 11146;
-
 pub const CONFIG_PATH: &str = "./config.toml";
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const COVERAGE_DEFAULT_DAYS: u64 = 27;
+/// Key of the upload targets of the combined workbook
+const COMBINED_KEY: &str = "combined";
+/// Title of the combined workbook in its file name
+const COMBINED_TITLE: &str = "Berichte";
 
-fn main() -> Result<()> {
+/// Exit code of errors without a more specific code
+const EXIT_FAILURE: u8 = 1;
+/// Exit code of rejected divera credentials
+const EXIT_AUTHENTICATION: u8 = 77;
+/// Exit code of failures which might succeed when retried later
+const EXIT_TEMPORARY: u8 = 75;
+/// Exit code of errors reported by divera
+const EXIT_UNAVAILABLE: u8 = 69;
+/// Exit code of divera responses which do not match the expected schema
+const EXIT_DATA: u8 = 65;
+
+fn main() -> ExitCode {
     env_logger::init();
 
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {error:?}");
+            ExitCode::from(exit_code(&error))
+        }
+    }
+}
+
+fn exit_code(error: &anyhow::Error) -> u8 {
+    let Some(error) = error
+        .chain()
+        .find_map(|error| error.downcast_ref::<DiveraError>())
+    else {
+        return EXIT_FAILURE;
+    };
+
+    match error {
+        DiveraError::Authentication(_) => EXIT_AUTHENTICATION,
+        error if error.is_transient() => EXIT_TEMPORARY,
+        // An invalid proxy or client setting fails before any request is sent
+        DiveraError::Network(error) if error.is_builder() => EXIT_FAILURE,
+        // The connection was established, but the request or response was interrupted
+        DiveraError::Network(_) => EXIT_TEMPORARY,
+        DiveraError::Status { .. } | DiveraError::Api(_) => EXIT_UNAVAILABLE,
+        DiveraError::Deserialize { .. } => EXIT_DATA,
+        _ => EXIT_FAILURE,
+    }
+}
+
+fn run() -> Result<()> {
     let cli = Cli::parse();
     let config_path = cli.config.unwrap_or(CONFIG_PATH.to_string());
     let config_path = Path::new(&config_path);
@@ -37,131 +102,637 @@ fn main() -> Result<()> {
                 bail!("Config already exists. Aborting");
             }
 
-            let config = Config::new(
-                cmd.divera_username,
-                cmd.divera_password,
-                cmd.webdav_username,
-                cmd.webdav_password,
-                cmd.webdav_directory,
-            );
+            let config = init(cmd)?;
             config.write(config_path)?;
         }
         Commands::ReportTypes => {
             let config = Config::read(config_path)?;
-            let login = divera::login(&config.divera.username, &config.divera.password)?;
-            let report_types = divera::report_types(&login.user.access_token)?;
+            let client = login(&config)?;
+            let report_types = client.report_types()?;
             println!("{report_types}");
         }
 
         Commands::Report(cmd) => {
             let config = Config::read(config_path)?;
-            let login = divera::login(&config.divera.username, &config.divera.password)?;
-            let all = divera::pull_all(&login.user.access_token)?;
+            let client = login(&config)?;
+            let all = client.pull_all()?;
             let users = all.cluster.consumer;
             let report_types = all.cluster.reporttypes;
+            let max_errors = cli.max_errors.unwrap_or(config.parsing.max_errors);
+            let mut tolerance = Tolerance {
+                lenient: cli.lenient || cli.max_errors.is_some() || config.parsing.lenient,
+                max_errors,
+                errors: 0,
+            };
 
             match cmd {
                 cli::Report::Absences(arguments) => {
                     let reports_name = "absences";
-                    let reports: Vec<AbsentReport> = get_reports(
-                        REPORT_ID_ABSENCES,
-                        &login.user.access_token,
+                    let range = date_range(&arguments.filter)?;
+                    let mut reports: Parsed<Vec<AbsentReport>> = get_reports(
+                        &client,
+                        &config.mapping.absences,
                         &report_types,
                         &users,
+                        &mut tolerance,
                     )
                     .context(format!("Failed to create {reports_name} reports"))?;
-                    handle_report_arguments(reports, &config, arguments)
-                        .context(format!("Failed handle {reports_name} reports arguments"))?;
+                    reports.retain_range(&range);
+                    handle_report_arguments(
+                        reports,
+                        &config,
+                        ReportKind::Absences.key(),
+                        arguments.arguments,
+                    )
+                    .context(format!("Failed handle {reports_name} reports arguments"))?;
                 }
                 cli::Report::Roster(arguments) => {
                     let reports_name = "roster";
-                    let reports: Vec<RosterReport> = get_reports(
-                        REPORT_ID_ROSTER,
-                        &login.user.access_token,
+                    let reports: Parsed<Vec<RosterReport>> = get_reports(
+                        &client,
+                        &config.mapping.roster,
                         &report_types,
                         &users,
+                        &mut tolerance,
                     )
                     .context(format!("Failed to create {reports_name} reports"))?;
-                    handle_report_arguments(reports, &config, arguments)
+                    handle_report_arguments(reports, &config, ReportKind::Roster.key(), arguments)
                         .context(format!("Failed handle {reports_name} reports arguments"))?;
                 }
                 cli::Report::Station(arguments) => {
                     let reports_name = "station";
-                    let reports: Vec<StationReport> = get_reports(
-                        REPORT_ID_STATION,
-                        &login.user.access_token,
+                    let reports: Parsed<Vec<StationReport>> = get_reports(
+                        &client,
+                        &config.mapping.station,
                         &report_types,
                         &users,
+                        &mut tolerance,
                     )
                     .context(format!("Failed to create {reports_name} reports"))?;
-                    handle_report_arguments(reports, &config, arguments)
+                    handle_report_arguments(reports, &config, ReportKind::Station.key(), arguments)
                         .context(format!("Failed handle {reports_name} reports arguments"))?;
                 }
                 cli::Report::FireOperation(arguments) => {
                     let reports_name = "fire operation";
-                    let reports: Vec<FireOperationReport> = get_reports(
-                        REPORT_ID_FIRE_OPERATION,
-                        &login.user.access_token,
+                    let range = date_range(&arguments.filter)?;
+                    let mut reports: Parsed<Vec<FireOperationReport>> = get_reports(
+                        &client,
+                        &config.mapping.fire_operation,
                         &report_types,
                         &users,
+                        &mut tolerance,
                     )
                     .context(format!("Failed to create {reports_name} reports"))?;
-                    handle_report_arguments(reports, &config, arguments)
-                        .context(format!("Failed handle {reports_name} reports arguments"))?;
+                    reports.retain_range(&range);
+                    if arguments.summary {
+                        handle_report_arguments(
+                            reports.map(FireOperationSummary::new),
+                            &config,
+                            ReportKind::FireOperation.key(),
+                            arguments.arguments,
+                        )
+                    } else {
+                        handle_report_arguments(
+                            reports,
+                            &config,
+                            ReportKind::FireOperation.key(),
+                            arguments.arguments,
+                        )
+                    }
+                    .context(format!("Failed handle {reports_name} reports arguments"))?;
+                }
+                cli::Report::ById(cmd) => {
+                    let mapping = Mapping::generic(cmd.id);
+                    let reports: Parsed<GenericReports> =
+                        get_reports(&client, &mapping, &report_types, &users, &mut tolerance)
+                            .context(format!("Failed to create reports of type {}", cmd.id))?;
+                    handle_report_arguments(reports, &config, &cmd.id.to_string(), cmd.arguments)
+                        .context(format!(
+                        "Failed handle reports of type {} arguments",
+                        cmd.id
+                    ))?;
+                }
+                cli::Report::Coverage(cmd) => {
+                    let range = date_range(&cmd.filter)?;
+                    let from = range.from.unwrap_or_else(|| Local::now().date_naive());
+                    let to = range
+                        .to
+                        .unwrap_or_else(|| from + Days::new(COVERAGE_DEFAULT_DAYS));
+                    let range = DateRange::new(Some(from), Some(to))?;
+                    let absences: Parsed<Vec<AbsentReport>> = get_reports(
+                        &client,
+                        &config.mapping.absences,
+                        &report_types,
+                        &users,
+                        &mut tolerance,
+                    )
+                    .context("Failed to create absences reports")?;
+                    let minimum_strength = cmd
+                        .minimum_strength
+                        .unwrap_or(config.coverage.minimum_strength);
+                    let coverage = absences.try_map(|absences| {
+                        Coverage::new(&absences, &users, &range, minimum_strength)
+                    })?;
+                    handle_report_arguments(coverage, &config, "coverage", cmd.arguments)
+                        .context("Failed handle coverage arguments")?;
+                }
+                cli::Report::Compliance(cmd) => {
+                    let date = match cmd.date {
+                        Some(date) => parse_date(&date)?,
+                        None => Local::now().date_naive(),
+                    };
+                    let reports: Parsed<Vec<FireOperationReport>> = get_reports(
+                        &client,
+                        &config.mapping.fire_operation,
+                        &report_types,
+                        &users,
+                        &mut tolerance,
+                    )
+                    .context("Failed to create fire operation reports")?;
+                    let warning_days = cmd.warning_days.unwrap_or(config.compliance.warning_days);
+                    let compliance = reports.map(|reports| {
+                        Compliance::new(&reports, &users, cmd.all_members, date, warning_days)
+                    });
+                    handle_report_arguments(compliance, &config, "compliance", cmd.arguments)
+                        .context("Failed handle compliance arguments")?;
+                }
+                cli::Report::All(cmd) => {
+                    let temp_dir = tempdir().context("Failed to create temp dir")?;
+                    let directory = cmd
+                        .output
+                        .write
+                        .map(PathBuf::from)
+                        .unwrap_or_else(|| temp_dir.path().to_path_buf());
+                    let mut export = if cmd.output.print {
+                        AllExport::Print
+                    } else if let Some(template) = cmd.combined {
+                        let mut workbook = Workbook::new();
+                        workbook.read_only_recommended();
+                        let file_name = file_name::render(
+                            &template,
+                            COMBINED_TITLE,
+                            Local::now().date_naive(),
+                        )?;
+                        let file = ExportedFile {
+                            key: COMBINED_KEY.to_string(),
+                            path: directory.join(&file_name),
+                            file_name,
+                            fingerprint: String::default(),
+                            summary: Summary {
+                                title: COMBINED_TITLE.to_string(),
+                                entries: Vec::default(),
+                            },
+                        };
+                        AllExport::Combined(Box::new(workbook), file, Vec::default())
+                    } else {
+                        AllExport::Files {
+                            directory: directory.clone(),
+                            reports: config.reports.clone(),
+                            files: Vec::default(),
+                        }
+                    };
+
+                    for kind in &config.reports.enabled {
+                        match kind {
+                            ReportKind::Absences => get_reports::<Vec<AbsentReport>>(
+                                &client,
+                                &config.mapping.absences,
+                                &report_types,
+                                &users,
+                                &mut tolerance,
+                            )
+                            .and_then(|reports| export.add(kind.key(), reports)),
+                            ReportKind::Roster => get_reports::<Vec<RosterReport>>(
+                                &client,
+                                &config.mapping.roster,
+                                &report_types,
+                                &users,
+                                &mut tolerance,
+                            )
+                            .and_then(|reports| export.add(kind.key(), reports)),
+                            ReportKind::Station => get_reports::<Vec<StationReport>>(
+                                &client,
+                                &config.mapping.station,
+                                &report_types,
+                                &users,
+                                &mut tolerance,
+                            )
+                            .and_then(|reports| export.add(kind.key(), reports)),
+                            ReportKind::FireOperation => get_reports::<Vec<FireOperationReport>>(
+                                &client,
+                                &config.mapping.fire_operation,
+                                &report_types,
+                                &users,
+                                &mut tolerance,
+                            )
+                            .and_then(|reports| export.add(kind.key(), reports)),
+                        }
+                        .context(format!("Failed to export {kind:?} reports"))?;
+                    }
+                    for id in &config.reports.generic {
+                        let mapping = Mapping::generic(*id);
+                        get_reports::<GenericReports>(
+                            &client,
+                            &mapping,
+                            &report_types,
+                            &users,
+                            &mut tolerance,
+                        )
+                        .and_then(|reports| export.add(&id.to_string(), reports))
+                        .context(format!("Failed to export reports of type {id}"))?;
+                    }
+
+                    let files = export.finish().context("Failed to write reports")?;
+                    for file in files {
+                        if cmd.output.upload {
+                            config.destinations(&file.key)?.upload(
+                                &file.path,
+                                &file.file_name,
+                                &file.fingerprint,
+                                &file.summary,
+                            )?;
+                        } else {
+                            archive_written(&config, &directory, &file.file_name)?;
+                        }
+                    }
                 }
             };
+            tolerance.check()?;
         }
     };
     Ok(())
 }
 
+/// Builds the config from the init arguments.
+/// In interactive mode missing values are asked for and the connections are checked.
+fn init(cmd: cli::Init) -> Result<Config> {
+    if !cmd.interactive
+        && cmd.divera_username.is_some()
+        && cmd.divera_password.is_none()
+        && cmd.divera_password_file.is_none()
+    {
+        bail!("--divera-username requires --divera-password or --divera-password-file");
+    }
+    let url = match cmd.divera_url {
+        Some(url) => url,
+        None if cmd.interactive => prompt::text("Divera url", Some(BASE_URL))?,
+        None => BASE_URL.to_string(),
+    };
+    // Missing values are only possible in interactive mode
+    let authentication = match (cmd.divera_access_key, cmd.divera_username) {
+        (Some(access_key), _) => Authentication::AccessKey(access_key),
+        (None, Some(username)) => Authentication::Password {
+            username,
+            password: init_password(
+                cmd.divera_password,
+                cmd.divera_password_file,
+                "Divera password",
+            )?,
+        },
+        (None, None) => {
+            let access_key =
+                prompt::secret("Divera access key (empty to use username and password)")?;
+            if access_key.is_empty() {
+                Authentication::Password {
+                    username: prompt::text("Divera username", None)?,
+                    password: Password::value(prompt::secret("Divera password")?),
+                }
+            } else {
+                Authentication::AccessKey(access_key)
+            }
+        }
+    };
+    let webdav_username = match cmd.webdav_username {
+        Some(username) => username,
+        None => prompt::text("WebDAV username", None)?,
+    };
+    let webdav_password = init_password(
+        cmd.webdav_password,
+        cmd.webdav_password_file,
+        "WebDAV password",
+    )?;
+    let webdav_directory = match cmd.webdav_directory {
+        Some(directory) => directory,
+        None => prompt::text("WebDAV root directory url", None)?,
+    };
+
+    let mut config = Config::new(
+        authentication,
+        webdav_username,
+        webdav_password,
+        webdav_directory,
+    );
+    config.divera.url = url;
+    if cmd.interactive {
+        check_and_select_reports(&mut config)?;
+    }
+    Ok(config)
+}
+
+/// Password of the init arguments, a file is referenced instead of copying its content
+fn init_password(value: Option<String>, file: Option<String>, label: &str) -> Result<Password> {
+    match (value, file) {
+        (_, Some(file)) => Ok(Password::file(file)),
+        (Some(value), None) => Ok(Password::value(value)),
+        (None, None) => Ok(Password::value(prompt::secret(label)?)),
+    }
+}
+
+/// Checks the divera login and the webdav connection
+/// and lets the user pick the reports of the available report types
+fn check_and_select_reports(config: &mut Config) -> Result<()> {
+    eprintln!("Checking divera login");
+    let client = login(config).context("Divera login failed, the config was not written")?;
+    if let Some(webdav) = &config.webdav {
+        eprintln!("Checking webdav connection");
+        let settings = config.divera.settings();
+        WebDavTarget::new(
+            DEFAULT_TARGET,
+            webdav.clone(),
+            settings.connect_timeout,
+            settings.timeout,
+        )?
+        .check()
+        .context("WebDAV connection failed, the config was not written")?;
+    }
+
+    let report_types = client
+        .report_types()
+        .context("Failed to fetch report types")?;
+    println!("{report_types}");
+
+    let mappings = [
+        (ReportKind::Absences, &config.mapping.absences),
+        (ReportKind::Roster, &config.mapping.roster),
+        (ReportKind::Station, &config.mapping.station),
+        (ReportKind::FireOperation, &config.mapping.fire_operation),
+    ];
+    let mut enabled = Vec::default();
+    for (kind, mapping) in mappings {
+        let Some(report_type) = report_types.items.get(&mapping.report_type) else {
+            continue;
+        };
+        let question = format!("Enable {kind:?} reports of \"{}\"?", report_type.name);
+        if prompt::confirm(&question, true)? {
+            enabled.push(kind);
+        }
+    }
+
+    let mut generic = Vec::default();
+    let ids = prompt::text(
+        "Ids of further report types to export (comma separated)",
+        None,
+    )?;
+    for id in ids.split(',').map(str::trim).filter(|id| !id.is_empty()) {
+        let id: i64 = id
+            .parse()
+            .with_context(|| format!("Invalid report type id \"{id}\""))?;
+        if !report_types.items.contains_key(&id) {
+            bail!("Unknown report type {id}");
+        }
+        generic.push(id);
+    }
+
+    config.reports.enabled = enabled;
+    config.reports.generic = generic;
+    Ok(())
+}
+
+fn login(config: &Config) -> Result<DiveraClient> {
+    let divera = &config.divera;
+    let mut client = DiveraClient::new(&divera.url, divera.settings())?;
+    match divera.authentication()? {
+        Authentication::AccessKey(access_key) => client = client.with_access_token(&access_key),
+        Authentication::Password { username, password } => {
+            let password = password
+                .resolve()
+                .context("Failed to read divera password")?;
+            match &divera.token_cache {
+                Some(path) => client.login_cached(&username, &password, &TokenCache::new(path))?,
+                None => {
+                    client.login(&username, &password)?;
+                }
+            }
+        }
+    };
+    Ok(client)
+}
+
 fn get_reports<T: Reports>(
-    id: i64,
-    access_token: &str,
+    client: &DiveraClient,
+    mapping: &Mapping,
     report_types: &ReportTypes,
     users: &HashMap<String, Consumer>,
-) -> Result<T> {
-    let reports = divera::reports(&access_token, id).context("Failed to fetch reports")?;
-    let report_type = report_types.items.get(&id).cloned().unwrap();
-    let reports =
-        T::new_from_reports(&report_type, reports, users).context("Failed to create reports")?;
+    tolerance: &mut Tolerance,
+) -> Result<Parsed<T>> {
+    let id = mapping.report_type;
+    let reports = client.reports(id).context("Failed to fetch reports")?;
+    let report_type = report_types
+        .items
+        .get(&id)
+        .cloned()
+        .ok_or_else(|| anyhow!("Unknown report type {id}"))?;
+    let reports = T::new_from_reports(&report_type, reports, users, mapping, tolerance.lenient)
+        .context("Failed to create reports")?;
+    tolerance.errors += reports.errors.len();
 
     Ok(reports)
 }
 
+/// Number of malformed reports which are skipped before failing
+struct Tolerance {
+    lenient: bool,
+    max_errors: usize,
+    errors: usize,
+}
+
+impl Tolerance {
+    fn check(&self) -> Result<()> {
+        if self.errors > self.max_errors {
+            bail!(
+                "{} reports could not be parsed, at most {} are tolerated",
+                self.errors,
+                self.max_errors
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Destination of the reports exported by `report all`
+enum AllExport {
+    Print,
+    /// Written files in the directory, named by the file name templates of the reports
+    Files {
+        directory: PathBuf,
+        reports: config::Reports,
+        files: Vec<ExportedFile>,
+    },
+    /// Workbook, its file and the fingerprints of its worksheets
+    Combined(Box<Workbook>, ExportedFile, Vec<String>),
+}
+
+/// File written by `report all`
+struct ExportedFile {
+    /// Selects the upload targets of the file
+    key: String,
+    /// Name relative to the directory, which may contain subdirectories
+    file_name: String,
+    path: PathBuf,
+    fingerprint: String,
+    /// Reports of the file, listed in mails
+    summary: Summary,
+}
+
+impl AllExport {
+    /// The key selects the upload targets of the written file
+    fn add(&mut self, key: &str, reports: impl Export) -> Result<()> {
+        match self {
+            AllExport::Print => {
+                println!("{}", reports.title());
+                reports.print();
+            }
+            AllExport::Files {
+                directory,
+                reports: settings,
+                files,
+            } => {
+                let today = Local::now().date_naive();
+                let file_name =
+                    file_name::render(settings.file_name(key), &reports.title(), today)?;
+                let path = directory.join(&file_name);
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)
+                        .with_context(|| format!("Failed to create {}", parent.display()))?;
+                }
+                let fingerprint = reports.file_fingerprint(OutputFormat::Xlsx)?;
+                let summary = reports.summary()?;
+                reports.write_xlsx(&path)?;
+                files.push(ExportedFile {
+                    key: key.to_string(),
+                    file_name,
+                    path,
+                    fingerprint,
+                    summary,
+                });
+            }
+            AllExport::Combined(workbook, file, fingerprints) => {
+                fingerprints.push(reports.fingerprint()?);
+                // Entries of the worksheets are told apart by their title
+                let summary = reports.summary()?;
+                file.summary.entries.extend(
+                    summary
+                        .entries
+                        .into_iter()
+                        .map(|entry| format!("{}: {entry}", summary.title)),
+                );
+                reports.add_worksheet(workbook)?
+            }
+        };
+        Ok(())
+    }
+
+    /// Returns the written files
+    fn finish(self) -> Result<Vec<ExportedFile>> {
+        let files = match self {
+            AllExport::Print => Vec::default(),
+            AllExport::Files { files, .. } => files,
+            AllExport::Combined(mut workbook, mut file, fingerprints) => {
+                if let Some(parent) = file.path.parent() {
+                    fs::create_dir_all(parent)
+                        .with_context(|| format!("Failed to create {}", parent.display()))?;
+                }
+                workbook.save(&file.path)?;
+                file.fingerprint = fingerprint(&fingerprints)?;
+                vec![file]
+            }
+        };
+        Ok(files)
+    }
+}
+
+/// Keeps a dated copy of a written file in the archive directory next to it
+fn archive_written(config: &Config, directory: &Path, file_name: &str) -> Result<()> {
+    let Some(archive) = &config.reports.archive else {
+        return Ok(());
+    };
+    let target = DirectoryTarget::new(
+        "write",
+        Directory::new(directory.to_string_lossy().to_string()),
+    );
+    archive::store(
+        archive,
+        &target,
+        &directory.join(file_name),
+        file_name,
+        Local::now().date_naive(),
+    )
+    .with_context(|| format!("Failed to archive \"{file_name}\""))
+}
+
+fn date_range(filter: &cli::DateFilter) -> Result<DateRange> {
+    if filter.last_month {
+        return DateRange::previous_month(Local::now().date_naive());
+    }
+    if let Some(year) = filter.year {
+        return DateRange::year(year);
+    }
+
+    let from = filter.from.as_deref().map(parse_date).transpose()?;
+    let to = filter.to.as_deref().map(parse_date).transpose()?;
+    DateRange::new(from, to)
+}
+
+fn parse_date(date: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(date, DATE_FORMAT)
+        .with_context(|| format!("Failed to parse date \"{date}\""))
+}
+
+/// The key selects the upload targets of the reports in the config
 fn handle_report_arguments(
-    reports: impl Reports,
+    reports: impl Export,
     config: &Config,
+    key: &str,
     arguments: PrintWriteUpload,
 ) -> Result<()> {
+    let format = arguments.format.map(OutputFormat::from).unwrap_or_default();
+    let today = Local::now().date_naive();
     if arguments.print {
-        reports.print();
+        match arguments.format {
+            Some(_) => reports
+                .write_text(io::stdout().lock(), format)
+                .context("Failed to print reports")?,
+            None => reports.print(),
+        }
     } else if let Some(output_path) = arguments.write {
+        let path = PathBuf::from(file_name::render(&output_path, &reports.title(), today)?);
         reports
-            .write_xlsx(Path::new(&output_path))
-            .context("Failed to write fire_operation reports to xlsx")?;
+            .write(&path, format)
+            .context("Failed to write reports")?;
+        let directory = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        archive_written(config, directory, &file_name)?;
     } else if let Some(file_name) = arguments.upload {
+        let file_name = file_name::render(&file_name, &reports.title(), today)?;
         reports
-            .upload(&file_name, config.webdav.clone())
-            .context("Failed to upload station reports")?;
+            .upload(&file_name, format, &config.destinations(key)?)
+            .context("Failed to upload reports")?;
     }
 
     Ok(())
 }
 
-impl Display for divera::schema::response::ReportTypes {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut table = Table::new();
-        table
-            .set_header(vec!["ID", "Name", "Description"])
-            .set_content_arrangement(ContentArrangement::Dynamic);
-        for (id, item) in self.items.iter() {
-            table.add_row(vec![
-                id.to_string(),
-                item.name.clone().lines().collect(),
-                item.description.clone().lines().collect(),
-            ]);
+impl From<cli::Format> for OutputFormat {
+    fn from(format: cli::Format) -> Self {
+        match format {
+            cli::Format::Xlsx => OutputFormat::Xlsx,
+            cli::Format::Csv => OutputFormat::Csv,
+            cli::Format::Json => OutputFormat::Json,
+            cli::Format::Ndjson => OutputFormat::NdJson,
+            cli::Format::Ics => OutputFormat::Ics,
         }
-        f.write_str(&table.to_string())
     }
 }
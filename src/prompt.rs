@@ -0,0 +1,51 @@
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use anyhow::{bail, Context, Result};
+
+/// Asks for a line of text. An empty answer selects the default.
+pub fn text(label: &str, default: Option<&str>) -> Result<String> {
+    match default {
+        Some(default) => eprint!("{label} [{default}]: "),
+        None => eprint!("{label}: "),
+    }
+    let answer = read_line()?;
+    match (answer.is_empty(), default) {
+        (true, Some(default)) => Ok(default.to_string()),
+        _ => Ok(answer),
+    }
+}
+
+/// Asks for a secret without echoing it on a terminal
+pub fn secret(label: &str) -> Result<String> {
+    if !io::stdin().is_terminal() {
+        eprint!("{label}: ");
+        return read_line();
+    }
+    rpassword::prompt_password(format!("{label}: ")).context("Failed to read secret")
+}
+
+pub fn confirm(label: &str, default: bool) -> Result<bool> {
+    let options = if default { "Y/n" } else { "y/N" };
+    loop {
+        eprint!("{label} [{options}]: ");
+        match read_line()?.to_lowercase().as_str() {
+            "" => return Ok(default),
+            "y" | "yes" | "j" | "ja" => return Ok(true),
+            "n" | "no" | "nein" => return Ok(false),
+            _ => eprintln!("Please answer y or n"),
+        }
+    }
+}
+
+fn read_line() -> Result<String> {
+    io::stderr().flush()?;
+    let mut line = String::new();
+    let read = io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .context("Failed to read answer")?;
+    if read == 0 {
+        bail!("Input ended before all questions were answered");
+    }
+    Ok(line.trim().to_string())
+}
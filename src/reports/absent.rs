@@ -1,12 +1,18 @@
-use std::{collections::HashMap, fmt::Display, path::Path};
-
-use super::{parse_date, parse_string, set_table, Reports};
-use crate::divera::schema::response;
+use std::{collections::HashMap, fmt::Display, io::Write};
+
+use super::{
+    columns,
+    ical::{Calendar, Event},
+    parse_date, parse_reports, parse_string, set_table, DateRange, Dated, Export, Parsed, Reports,
+};
+use crate::{config::Mapping, divera::schema::response};
 use anyhow::{bail, Context, Result};
 use chrono::NaiveDate;
 use comfy_table;
 use rust_xlsxwriter::{Format, Workbook};
+use serde::Serialize;
 
+const REPORT_TYPE_ID: i64 = 10538;
 const BEGIN_ID: &str = "10f05309-e584-4470-a0db-ce6bb15ade34";
 const END_ID: &str = "a9246571-63fd-4cdf-b6f1-77d93173b362";
 const NOTE_ID: &str = "29091ead-0dca-4546-830a-c4143e0886ec";
@@ -16,6 +22,15 @@ const REASON_PROFESSIONALLY_ID: &str = "1ad668bf-5a17-4f5d-b762-5ce9bb20c0d9";
 const REASON_TRAINING_ID: &str = "f4913db8-0112-40d8-8efa-dad361d8829b";
 const REASON_VACATION_ID: &str = "5c66e8a3-bb3b-455a-aeb9-a4982f774dd8";
 
+const BEGIN_KEY: &str = "begin";
+const END_KEY: &str = "end";
+const NOTE_KEY: &str = "note";
+const REASON_KEY: &str = "reason";
+const REASON_ILLNESS_KEY: &str = "illness";
+const REASON_PROFESSIONALLY_KEY: &str = "professionally";
+const REASON_TRAINING_KEY: &str = "training";
+const REASON_VACATION_KEY: &str = "vacation";
+
 const TITLE: &str = "Abwesenheiten";
 const BEGIN_TEXT: &str = "Von";
 const END_TEXT: &str = "Bis";
@@ -35,9 +50,12 @@ const ABSENT_REPORTS_HEADERS: [&str; 6] = [
     NOTE_TEXT,
 ];
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize)]
 pub struct AbsentReport {
     pub id: i64,
+    /// User cluster relation id of the member, which tells namesakes apart
+    #[serde(skip)]
+    pub user_id: i64,
     pub user: String,
     pub begin: NaiveDate,
     pub end: NaiveDate,
@@ -45,7 +63,8 @@ pub struct AbsentReport {
     pub note: String,
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Reason {
     Illness,
     Professionally,
@@ -59,28 +78,30 @@ impl AbsentReport {
         report_type: &response::ReportTypesItem,
         report: &response::Report,
         user: &response::Consumer,
+        mapping: &Mapping,
     ) -> Result<Self> {
         let mut absent_report = AbsentReport {
             id: report.id,
+            user_id: report.user_cluster_relation_id,
             user: user.stdformat_name.clone(),
             ..Default::default()
         };
 
         for (field, field_type) in report.fields.iter().zip(report_type.fields.iter()) {
-            match field_type.id.as_str() {
-                BEGIN_ID => {
+            match mapping.field(&field_type.id) {
+                Some(BEGIN_KEY) => {
                     absent_report.begin =
                         parse_date(field).context("Failed to get begin of absent report")?
                 }
-                END_ID => {
+                Some(END_KEY) => {
                     absent_report.end =
                         parse_date(field).context("Failed to get end of absent report")?
                 }
-                REASON_ID => {
+                Some(REASON_KEY) => {
                     let id = parse_string(field).context("Failed to get reason id")?;
-                    absent_report.reason = Reason::new(&id)?;
+                    absent_report.reason = Reason::new(&id, mapping)?;
                 }
-                NOTE_ID => {
+                Some(NOTE_KEY) => {
                     absent_report.note = parse_string(field).context("Failed to get note")?;
                 }
                 _ => bail!("Unknown absent report type \"{}\"", field_type.name),
@@ -89,28 +110,52 @@ impl AbsentReport {
         Ok(absent_report)
     }
 }
+pub fn default_mapping() -> Mapping {
+    Mapping::new(
+        REPORT_TYPE_ID,
+        &[
+            (BEGIN_KEY, BEGIN_ID),
+            (END_KEY, END_ID),
+            (NOTE_KEY, NOTE_ID),
+            (REASON_KEY, REASON_ID),
+        ],
+        &[(
+            REASON_KEY,
+            &[
+                (REASON_ILLNESS_KEY, REASON_ILLNESS_ID),
+                (REASON_PROFESSIONALLY_KEY, REASON_PROFESSIONALLY_ID),
+                (REASON_TRAINING_KEY, REASON_TRAINING_ID),
+                (REASON_VACATION_KEY, REASON_VACATION_ID),
+            ],
+        )],
+    )
+}
+
 impl Reports for Vec<AbsentReport> {
     fn new_from_reports(
         report_type: &response::ReportTypesItem,
         reports: response::Reports,
         users: &HashMap<String, response::Consumer>,
-    ) -> Result<Self>
+        mapping: &Mapping,
+        lenient: bool,
+    ) -> Result<Parsed<Self>>
     where
         Self: Sized,
     {
-        let mut absent_reports: Vec<AbsentReport> = Vec::default();
-
-        for report in reports.items {
-            let user = users
-                .get(&report.user_cluster_relation_id.to_string())
-                .cloned()
-                .unwrap_or_default();
-            let absent_report = AbsentReport::new_from_report(&report_type, &report, &user)
-                .context("Failed to create absent report")?;
-            absent_reports.push(absent_report);
-        }
+        parse_reports(reports, users, lenient, |report, user| {
+            AbsentReport::new_from_report(report_type, report, user, mapping)
+                .context("Failed to create absent report")
+        })
+    }
+}
+
+impl Export for Vec<AbsentReport> {
+    fn title(&self) -> String {
+        TITLE.to_string()
+    }
 
-        Ok(absent_reports)
+    fn columns(&self) -> Vec<String> {
+        columns::<AbsentReport>()
     }
 
     fn print(self) {
@@ -132,10 +177,7 @@ impl Reports for Vec<AbsentReport> {
         println!("{table}");
     }
 
-    fn write_xlsx(self, path: &Path) -> Result<()> {
-        let mut workbook = Workbook::new();
-        workbook.read_only_recommended();
-
+    fn add_worksheet(self, workbook: &mut Workbook) -> Result<()> {
         let worksheet = workbook.add_worksheet().set_name(TITLE)?;
         set_table(worksheet, &ABSENT_REPORTS_HEADERS, self.len())?;
 
@@ -150,18 +192,37 @@ impl Reports for Vec<AbsentReport> {
             worksheet.write(row, 5, report.note)?;
         }
         worksheet.autofit();
-        workbook.save(path)?;
         Ok(())
     }
+
+    fn write_ics(&self, writer: impl Write) -> Result<()> {
+        let mut calendar = Calendar::new(writer, TITLE)?;
+        for report in self {
+            calendar.add_event(&Event {
+                uid: &format!("absence-{}@divera-reports", report.id),
+                begin: report.begin,
+                end: report.end,
+                summary: &format!("{}: {}", report.user, report.reason),
+                description: &report.note,
+            })?;
+        }
+        calendar.finish()
+    }
+}
+
+impl Dated for Vec<AbsentReport> {
+    fn retain_range(&mut self, range: &DateRange) {
+        self.retain(|report| range.overlaps(report.begin, report.end));
+    }
 }
 
 impl Reason {
-    pub fn new(id: &str) -> Result<Self> {
-        let variant = match id {
-            REASON_TRAINING_ID => Self::Training,
-            REASON_PROFESSIONALLY_ID => Self::Professionally,
-            REASON_ILLNESS_ID => Self::Illness,
-            REASON_VACATION_ID => Self::Vacation,
+    pub fn new(id: &str, mapping: &Mapping) -> Result<Self> {
+        let variant = match mapping.option(REASON_KEY, id) {
+            Some(REASON_TRAINING_KEY) => Self::Training,
+            Some(REASON_PROFESSIONALLY_KEY) => Self::Professionally,
+            Some(REASON_ILLNESS_KEY) => Self::Illness,
+            Some(REASON_VACATION_KEY) => Self::Vacation,
             _ => bail!("Unknow type variant \"{}\"", id),
         };
 
@@ -0,0 +1,131 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{anyhow, Context, Result};
+use rust_xlsxwriter::Workbook;
+
+use super::{
+    absent::AbsentReport, fire_operation::FireOperationReport, generic::GenericReports,
+    roster::RosterReport, station::StationReport, Export, Parsed, Reports,
+};
+use crate::{
+    config::{Config, Mapping, ReportKind},
+    divera::{schema::response, AsyncDiveraClient},
+};
+
+/// Parsed reports of an enabled report kind or generic report type
+pub enum EnabledReports {
+    Absences(Parsed<Vec<AbsentReport>>),
+    Roster(Parsed<Vec<RosterReport>>),
+    Station(Parsed<Vec<StationReport>>),
+    FireOperation(Parsed<Vec<FireOperationReport>>),
+    Generic(i64, Parsed<GenericReports>),
+}
+
+impl EnabledReports {
+    /// Key of the reports in the config, which selects their upload targets and file name
+    pub fn key(&self) -> String {
+        match self {
+            EnabledReports::Absences(_) => ReportKind::Absences.key().to_string(),
+            EnabledReports::Roster(_) => ReportKind::Roster.key().to_string(),
+            EnabledReports::Station(_) => ReportKind::Station.key().to_string(),
+            EnabledReports::FireOperation(_) => ReportKind::FireOperation.key().to_string(),
+            EnabledReports::Generic(id, _) => id.to_string(),
+        }
+    }
+
+    /// Number of skipped malformed reports
+    pub fn errors(&self) -> usize {
+        match self {
+            EnabledReports::Absences(reports) => reports.errors.len(),
+            EnabledReports::Roster(reports) => reports.errors.len(),
+            EnabledReports::Station(reports) => reports.errors.len(),
+            EnabledReports::FireOperation(reports) => reports.errors.len(),
+            EnabledReports::Generic(_, reports) => reports.errors.len(),
+        }
+    }
+
+    pub fn add_worksheet(self, workbook: &mut Workbook) -> Result<()> {
+        match self {
+            EnabledReports::Absences(reports) => reports.add_worksheet(workbook),
+            EnabledReports::Roster(reports) => reports.add_worksheet(workbook),
+            EnabledReports::Station(reports) => reports.add_worksheet(workbook),
+            EnabledReports::FireOperation(reports) => reports.add_worksheet(workbook),
+            EnabledReports::Generic(_, reports) => reports.add_worksheet(workbook),
+        }
+    }
+}
+
+/// Fetches the reports of all enabled report kinds and generic report types of the config
+/// concurrently, like `report all` does one after the other
+pub async fn fetch_enabled(
+    client: &AsyncDiveraClient,
+    config: &Config,
+    lenient: bool,
+) -> Result<Vec<EnabledReports>> {
+    let all = client.pull_all().await.context("Failed to fetch users")?;
+    let kinds = &config.reports.enabled;
+    let mut mappings: Vec<Mapping> = kinds
+        .iter()
+        .map(|kind| config.mapping.get(*kind).clone())
+        .collect();
+    mappings.extend(config.reports.generic.iter().copied().map(Mapping::generic));
+    let ids: Vec<i64> = mappings.iter().map(|mapping| mapping.report_type).collect();
+    let fetched = client
+        .reports_of(&ids)
+        .await
+        .context("Failed to fetch reports")?;
+
+    let parser = Parser {
+        report_types: &all.cluster.reporttypes,
+        users: &all.cluster.consumer,
+        lenient,
+    };
+    let mut enabled = Vec::with_capacity(fetched.len());
+    for (index, (mapping, reports)) in mappings.iter().zip(fetched).enumerate() {
+        let parsed = match kinds.get(index) {
+            Some(ReportKind::Absences) => EnabledReports::Absences(parser.parse(mapping, reports)?),
+            Some(ReportKind::Roster) => EnabledReports::Roster(parser.parse(mapping, reports)?),
+            Some(ReportKind::Station) => EnabledReports::Station(parser.parse(mapping, reports)?),
+            Some(ReportKind::FireOperation) => {
+                EnabledReports::FireOperation(parser.parse(mapping, reports)?)
+            }
+            None => EnabledReports::Generic(mapping.report_type, parser.parse(mapping, reports)?),
+        };
+        enabled.push(parsed);
+    }
+    Ok(enabled)
+}
+
+/// Writes the reports into a single xlsx file with one worksheet per report type
+pub fn write_combined(reports: Vec<EnabledReports>, path: &Path) -> Result<()> {
+    let mut workbook = Workbook::new();
+    workbook.read_only_recommended();
+    for reports in reports {
+        reports.add_worksheet(&mut workbook)?;
+    }
+    workbook.save(path)?;
+    Ok(())
+}
+
+struct Parser<'a> {
+    report_types: &'a response::ReportTypes,
+    users: &'a HashMap<String, response::Consumer>,
+    lenient: bool,
+}
+
+impl Parser<'_> {
+    fn parse<T: Reports>(
+        &self,
+        mapping: &Mapping,
+        reports: response::Reports,
+    ) -> Result<Parsed<T>> {
+        let id = mapping.report_type;
+        let report_type = self
+            .report_types
+            .items
+            .get(&id)
+            .ok_or_else(|| anyhow!("Unknown report type {id}"))?;
+        T::new_from_reports(report_type, reports, self.users, mapping, self.lenient)
+            .with_context(|| format!("Failed to create reports of type {id}"))
+    }
+}
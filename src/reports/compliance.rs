@@ -0,0 +1,218 @@
+use std::{collections::HashMap, fmt::Display};
+
+use anyhow::Result;
+use chrono::{Days, Months, NaiveDate};
+use comfy_table::{Cell, Color, ContentArrangement, Table};
+use rust_xlsxwriter::{Format, Workbook};
+use serde::{Serialize, Serializer};
+
+use super::{
+    columns,
+    fire_operation::{FireOperationReport, Type},
+    set_table, Export,
+};
+use crate::divera::schema::response;
+
+/// Time after which an exercise or operation under breathing apparatus has to be repeated
+const QUALIFICATION_MONTHS: u32 = 12;
+
+const TITLE: &str = "Atemschutz Nachweis";
+const USER_TEXT: &str = "Mitglied";
+const LAST_DATE_TEXT: &str = "Letzter Nachweis";
+const LAST_TYPE_TEXT: &str = "Art";
+const DUE_TEXT: &str = "Fällig am";
+const STATUS_TEXT: &str = "Status";
+const STATUS_COMPLIANT_TEXT: &str = "Erfüllt";
+const STATUS_DUE_SOON_TEXT: &str = "Bald fällig";
+const STATUS_OVERDUE_TEXT: &str = "Überfällig";
+
+const COMPLIANCE_HEADERS: [&str; 5] = [
+    USER_TEXT,
+    LAST_DATE_TEXT,
+    LAST_TYPE_TEXT,
+    DUE_TEXT,
+    STATUS_TEXT,
+];
+
+/// Last qualifying exercise or operation of every breathing apparatus wearer
+#[derive(Clone, Debug, Default)]
+pub struct Compliance {
+    pub entries: Vec<ComplianceEntry>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ComplianceEntry {
+    pub user: String,
+    pub last_date: Option<NaiveDate>,
+    pub last_type: Option<Type>,
+    pub due: Option<NaiveDate>,
+    pub status: Status,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    /// Also the status of wearers without any report
+    #[default]
+    Overdue,
+    DueSoon,
+    Compliant,
+}
+
+impl Compliance {
+    /// Wearers are the members with at least one report, or all members if `all_members` is set.
+    /// Reports of users which are no members anymore and reports after the date are ignored.
+    pub fn new(
+        reports: &[FireOperationReport],
+        users: &HashMap<String, response::Consumer>,
+        all_members: bool,
+        date: NaiveDate,
+        warning_days: u64,
+    ) -> Self {
+        let mut last_reports: HashMap<String, &FireOperationReport> = HashMap::default();
+        for report in reports.iter().filter(|report| report.date <= date) {
+            let last = last_reports
+                .entry(report.user_id.to_string())
+                .or_insert(report);
+            if report.date > last.date {
+                *last = report;
+            }
+        }
+
+        let mut entries: Vec<ComplianceEntry> = users
+            .iter()
+            .filter_map(|(id, user)| {
+                let last = last_reports.get(id);
+                if last.is_none() && !all_members {
+                    return None;
+                }
+                Some(ComplianceEntry::new(
+                    &user.stdformat_name,
+                    last.copied(),
+                    date,
+                    warning_days,
+                ))
+            })
+            .collect();
+        entries.sort_by(|a, b| (a.status, a.due, &a.user).cmp(&(b.status, b.due, &b.user)));
+
+        Self { entries }
+    }
+}
+
+impl ComplianceEntry {
+    fn new(
+        user: &str,
+        last: Option<&FireOperationReport>,
+        date: NaiveDate,
+        warning_days: u64,
+    ) -> Self {
+        let due = last.and_then(|last| {
+            last.date
+                .checked_add_months(Months::new(QUALIFICATION_MONTHS))
+        });
+        let status = match due {
+            Some(due) if due < date => Status::Overdue,
+            Some(due) if due <= date + Days::new(warning_days) => Status::DueSoon,
+            Some(_) => Status::Compliant,
+            None => Status::Overdue,
+        };
+
+        Self {
+            user: user.to_string(),
+            last_date: last.map(|last| last.date),
+            last_type: last.map(|last| last.r#type.clone()),
+            due,
+            status,
+        }
+    }
+}
+
+impl Serialize for Compliance {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.entries.serialize(serializer)
+    }
+}
+
+impl Export for Compliance {
+    fn title(&self) -> String {
+        TITLE.to_string()
+    }
+
+    fn columns(&self) -> Vec<String> {
+        columns::<ComplianceEntry>()
+    }
+
+    fn print(self) {
+        let mut table = Table::new();
+        table.set_content_arrangement(ContentArrangement::Dynamic);
+        table.set_header(COMPLIANCE_HEADERS);
+        for entry in self.entries {
+            let color = match entry.status {
+                Status::Overdue => Color::Red,
+                Status::DueSoon => Color::Yellow,
+                Status::Compliant => Color::Reset,
+            };
+            table.add_row(vec![
+                Cell::new(entry.user),
+                Cell::new(optional(entry.last_date)),
+                Cell::new(optional(entry.last_type)),
+                Cell::new(optional(entry.due)),
+                Cell::new(entry.status).fg(color),
+            ]);
+        }
+
+        println!("{table}");
+    }
+
+    fn add_worksheet(self, workbook: &mut Workbook) -> Result<()> {
+        let worksheet = workbook.add_worksheet().set_name(TITLE)?;
+        set_table(worksheet, &COMPLIANCE_HEADERS, self.entries.len())?;
+
+        let date_format = Format::new().set_num_format("dd.mm.yyyy");
+        let overdue_format = Format::new().set_font_color("#9C0006").set_bold();
+        let due_soon_format = Format::new().set_font_color("#9C5700");
+        for (index, entry) in self.entries.into_iter().enumerate() {
+            let row = (index + 1) as u32;
+            worksheet.write(row, 0, entry.user)?;
+            if let Some(last_date) = entry.last_date {
+                worksheet.write_datetime_with_format(row, 1, last_date, &date_format)?;
+            }
+            worksheet.write(row, 2, optional(entry.last_type))?;
+            if let Some(due) = entry.due {
+                worksheet.write_datetime_with_format(row, 3, due, &date_format)?;
+            }
+            match entry.status {
+                Status::Overdue => worksheet.write_with_format(
+                    row,
+                    4,
+                    entry.status.to_string(),
+                    &overdue_format,
+                )?,
+                Status::DueSoon => worksheet.write_with_format(
+                    row,
+                    4,
+                    entry.status.to_string(),
+                    &due_soon_format,
+                )?,
+                Status::Compliant => worksheet.write(row, 4, entry.status.to_string())?,
+            };
+        }
+        worksheet.autofit();
+        Ok(())
+    }
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Status::Overdue => f.write_str(STATUS_OVERDUE_TEXT),
+            Status::DueSoon => f.write_str(STATUS_DUE_SOON_TEXT),
+            Status::Compliant => f.write_str(STATUS_COMPLIANT_TEXT),
+        }
+    }
+}
+
+fn optional(value: Option<impl Display>) -> String {
+    value.map_or(String::default(), |value| value.to_string())
+}
@@ -0,0 +1,163 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{bail, Result};
+use chrono::NaiveDate;
+use comfy_table::{Cell, Color, ContentArrangement, Table};
+use rust_xlsxwriter::{
+    ConditionalFormat3ColorScale, ConditionalFormatCell, ConditionalFormatCellRule, Format,
+    Workbook,
+};
+use serde::{Serialize, Serializer};
+
+use super::{absent::AbsentReport, columns, set_table, DateRange, Export};
+use crate::divera::schema::response::Consumer;
+
+const TITLE: &str = "Verfügbarkeit";
+const DATE_TEXT: &str = "Datum";
+const ABSENT_TEXT: &str = "Abwesend";
+const AVAILABLE_TEXT: &str = "Verfügbar";
+const BELOW_MINIMUM_TEXT: &str = "Unterbesetzt";
+const ABSENT_MEMBERS_TEXT: &str = "Abwesende Mitglieder";
+const YES_TEXT: &str = "Ja";
+
+const COVERAGE_HEADERS: [&str; 5] = [
+    DATE_TEXT,
+    ABSENT_TEXT,
+    AVAILABLE_TEXT,
+    BELOW_MINIMUM_TEXT,
+    ABSENT_MEMBERS_TEXT,
+];
+
+/// Number of absent and available members for each day of a range
+#[derive(Clone, Debug, Default)]
+pub struct Coverage {
+    pub minimum_strength: usize,
+    pub days: Vec<CoverageDay>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct CoverageDay {
+    pub date: NaiveDate,
+    pub absent: usize,
+    pub available: usize,
+    pub below_minimum: bool,
+    pub absent_members: Vec<String>,
+}
+
+impl Coverage {
+    /// Members with several absences on the same day are only counted once.
+    /// Absences of users which are not among the members are ignored.
+    pub fn new(
+        absences: &[AbsentReport],
+        members: &HashMap<String, Consumer>,
+        range: &DateRange,
+        minimum_strength: usize,
+    ) -> Result<Self> {
+        let (Some(from), Some(to)) = (range.from, range.to) else {
+            bail!("Coverage needs a closed date range");
+        };
+
+        let days = from
+            .iter_days()
+            .take_while(|date| *date <= to)
+            .map(|date| {
+                let absent_members: BTreeMap<i64, &str> = absences
+                    .iter()
+                    .filter(|absence| absence.begin <= date && date <= absence.end)
+                    .filter(|absence| members.contains_key(&absence.user_id.to_string()))
+                    .map(|absence| (absence.user_id, absence.user.as_str()))
+                    .collect();
+                let absent = absent_members.len();
+                let available = members.len().saturating_sub(absent);
+                let mut absent_members: Vec<String> =
+                    absent_members.into_values().map(String::from).collect();
+                absent_members.sort();
+                CoverageDay {
+                    date,
+                    absent,
+                    available,
+                    below_minimum: available < minimum_strength,
+                    absent_members,
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            minimum_strength,
+            days,
+        })
+    }
+}
+
+impl Serialize for Coverage {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.days.serialize(serializer)
+    }
+}
+
+impl Export for Coverage {
+    fn title(&self) -> String {
+        TITLE.to_string()
+    }
+
+    fn columns(&self) -> Vec<String> {
+        columns::<CoverageDay>()
+    }
+
+    fn print(self) {
+        let mut table = Table::new();
+        table.set_content_arrangement(ContentArrangement::Dynamic);
+        table.set_header(COVERAGE_HEADERS);
+        for day in self.days {
+            let color = if day.below_minimum {
+                Color::Red
+            } else {
+                Color::Reset
+            };
+            table.add_row(vec![
+                Cell::new(day.date).fg(color),
+                Cell::new(day.absent).fg(color),
+                Cell::new(day.available).fg(color),
+                Cell::new(if day.below_minimum { YES_TEXT } else { "" }).fg(color),
+                Cell::new(day.absent_members.join(", ")),
+            ]);
+        }
+
+        println!("{table}");
+    }
+
+    fn add_worksheet(self, workbook: &mut Workbook) -> Result<()> {
+        let worksheet = workbook.add_worksheet().set_name(TITLE)?;
+        set_table(worksheet, &COVERAGE_HEADERS, self.days.len())?;
+
+        let date_format = Format::new().set_num_format("dd.mm.yyyy");
+        let last_row = self.days.len() as u32;
+        for (index, day) in self.days.into_iter().enumerate() {
+            let row = (index + 1) as u32;
+            worksheet.write_datetime_with_format(row, 0, day.date, &date_format)?;
+            worksheet.write(row, 1, day.absent as u32)?;
+            worksheet.write(row, 2, day.available as u32)?;
+            if day.below_minimum {
+                worksheet.write(row, 3, YES_TEXT)?;
+            }
+            worksheet.write(row, 4, day.absent_members.join(", "))?;
+        }
+
+        if last_row > 0 {
+            let heatmap = ConditionalFormat3ColorScale::new()
+                .set_minimum_color("#F8696B")
+                .set_midpoint_color("#FFEB84")
+                .set_maximum_color("#63BE7B");
+            worksheet.add_conditional_format(1, 2, last_row, 2, &heatmap)?;
+
+            let below_minimum = ConditionalFormatCell::new()
+                .set_rule(ConditionalFormatCellRule::LessThan(
+                    self.minimum_strength as u32,
+                ))
+                .set_format(Format::new().set_bold().set_font_color("#9C0006"));
+            worksheet.add_conditional_format(1, 2, last_row, 2, &below_minimum)?;
+        }
+        worksheet.autofit();
+        Ok(())
+    }
+}
@@ -0,0 +1,32 @@
+use anyhow::{bail, Result};
+use chrono::NaiveDate;
+
+/// Characters which can not be part of a file name
+const INVALID_FILE_NAME_CHARS: [char; 2] = ['/', '\\'];
+
+/// Replaces the placeholders of a file name template.
+/// Supported are `{title}`, `{date}`, `{year}`, `{month}` and `{day}`,
+/// e.g. `Abwesenheiten_{year}-{month}.xlsx`.
+pub fn render(template: &str, title: &str, date: NaiveDate) -> Result<String> {
+    let mut file_name = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        file_name.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            bail!("Unclosed placeholder in file name \"{template}\"");
+        };
+        let placeholder = &rest[start + 1..start + end];
+        let value = match placeholder {
+            "title" => title.replace(INVALID_FILE_NAME_CHARS, "-"),
+            "date" => date.format("%Y-%m-%d").to_string(),
+            "year" => date.format("%Y").to_string(),
+            "month" => date.format("%m").to_string(),
+            "day" => date.format("%d").to_string(),
+            _ => bail!("Unknown placeholder {{{placeholder}}} in file name \"{template}\""),
+        };
+        file_name.push_str(&value);
+        rest = &rest[start + end + 1..];
+    }
+    file_name.push_str(rest);
+    Ok(file_name)
+}
@@ -1,14 +1,25 @@
-use std::{collections::HashMap, fmt::Display, path::Path};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+};
 
 use anyhow::{bail, Context, Result};
-use chrono::NaiveDate;
+use chrono::{Datelike, NaiveDate};
 use comfy_table::{self, ContentArrangement, Table};
 use rust_xlsxwriter::Workbook;
+use serde::{Serialize, Serializer};
 use serde_json::Value;
 
-use super::{parse_date, parse_string, set_table, Reports};
-use crate::divera::schema::response::{self};
+use super::{
+    columns, parse_date, parse_reports, parse_string, set_table, DateRange, Dated, Export, Parsed,
+    Reports,
+};
+use crate::{
+    config::Mapping,
+    divera::schema::response::{self},
+};
 
+const REPORT_TYPE_ID: i64 = 11146;
 const ACTIVITIES_ID: &str = "0fb3a9ca-cf80-47ef-bb60-3a365b1877dc";
 const ACTIVITY_COBRA_ID: &str = "c5667814-1820-4a82-9272-3364c136a902";
 const ACTIVITY_FIRE_FIGHTING_ID: &str = "e3fdc401-8a3b-4e83-aef8-a2c75abab7a0";
@@ -26,6 +37,23 @@ const TYPE_ID: &str = "2ef454dc-336a-4af7-89f3-cd985998360b";
 const TYPE_OPERATION_ID: &str = "6481edc4-4754-4b28-a9b6-220154740fb7";
 const TYPE_TRAINING_ID: &str = "05d6be2c-9286-42e3-89e7-5c37cd418ffb";
 
+const ACTIVITIES_KEY: &str = "activities";
+const ACTIVITY_COBRA_KEY: &str = "cobra";
+const ACTIVITY_FIRE_FIGHTING_KEY: &str = "fire_fighting";
+const ACTIVITY_LEADING_KEY: &str = "leading";
+const ACTIVITY_RESCUING_KEY: &str = "rescuing";
+const ACTIVITY_ROOF_OPENING_KEY: &str = "roof_opening";
+const ACTIVITY_VENTILATION_KEY: &str = "ventilation";
+const DATE_KEY: &str = "date";
+const DOUBLE_BOTTLES_KEY: &str = "double_bottles";
+const DURATION_KEY: &str = "duration";
+const ISSUES_KEY: &str = "issues";
+const OPERATION_TYPE_KEY: &str = "operation_type";
+const SINGLE_BOTTLES_KEY: &str = "single_bottles";
+const TYPE_KEY: &str = "type";
+const TYPE_OPERATION_KEY: &str = "operation";
+const TYPE_TRAINING_KEY: &str = "training";
+
 const TITLE: &str = "Atemschutz Kurzbericht";
 const ACTIVITIES_TEXT: &str = "Tätigkeit";
 const ACTIVITY_COBRA_TEXT: &str = "Cobra Cold Cut";
@@ -44,9 +72,15 @@ const TYPE_TEXT: &str = "Art";
 const TYPE_OPERATION_TEXT: &str = "Einsatz";
 const TYPE_TRAINING_TEXT: &str = "Übung";
 
+const SUMMARY_TITLE: &str = "Atemschutz Auswertung";
+const SUMMARY_OPERATIONS_TEXT: &str = "Einsätze";
+const SUMMARY_TRAININGS_TEXT: &str = "Übungen";
+const USER_TEXT: &str = "Mitglied";
+const YEAR_TEXT: &str = "Jahr";
+
 const FIRE_OPERATION_REPORTS_HEADERS: [&str; 10] = [
     "ID",
-    "Mitglied",
+    USER_TEXT,
     DATE_TEXT,
     TYPE_TEXT,
     OPERATION_TPYE_TEXT,
@@ -57,9 +91,28 @@ const FIRE_OPERATION_REPORTS_HEADERS: [&str; 10] = [
     DOUBLE_BOTTLE_TEXT,
 ];
 
-#[derive(Clone, Debug, Default)]
+const SUMMARY_HEADERS: [&str; 13] = [
+    USER_TEXT,
+    YEAR_TEXT,
+    SUMMARY_OPERATIONS_TEXT,
+    SUMMARY_TRAININGS_TEXT,
+    DURATION_TEXT,
+    SINGLE_BOTTLE_TEXT,
+    DOUBLE_BOTTLE_TEXT,
+    ACTIVITY_COBRA_TEXT,
+    ACTIVITY_FIRE_FIGHTING_TEXT,
+    ACTIVITY_LEADING_TEXT,
+    ACTIVITY_RESCUING_TEXT,
+    ACTIVITY_ROOF_OPENING_TEXT,
+    ACTIVITY_VENTILATION_TEXT,
+];
+
+#[derive(Clone, Debug, Default, Serialize)]
 pub struct FireOperationReport {
     pub id: i64,
+    /// User cluster relation id of the member, which tells namesakes apart
+    #[serde(skip)]
+    pub user_id: i64,
     pub user: String,
     pub activities: Activities,
     pub date: NaiveDate,
@@ -71,17 +124,19 @@ pub struct FireOperationReport {
     pub single_bottles: i64,
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Type {
     #[default]
     Operation,
     Training,
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize)]
 pub struct Activities(Vec<Activity>);
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Activity {
     #[default]
     Cobra,
@@ -97,45 +152,47 @@ impl FireOperationReport {
         report_type: &response::ReportTypesItem,
         report: &response::Report,
         user: &response::Consumer,
+        mapping: &Mapping,
     ) -> Result<Self> {
         let mut fire_operation_report = FireOperationReport {
             id: report.id,
+            user_id: report.user_cluster_relation_id,
             user: user.stdformat_name.clone(),
             ..Default::default()
         };
 
         for (field, field_type) in report.fields.iter().zip(report_type.fields.iter()) {
-            match field_type.id.as_str() {
-                ACTIVITIES_ID => {
+            match mapping.field(&field_type.id) {
+                Some(ACTIVITIES_KEY) => {
                     fire_operation_report.activities =
-                        Activities::new(field).context("Failed to parse activity")?;
+                        Activities::new(field, mapping).context("Failed to parse activity")?;
                 }
-                DATE_ID => {
+                Some(DATE_KEY) => {
                     fire_operation_report.date =
                         parse_date(field).context("Failed to parse date")?;
                 }
 
-                DOUBLE_BOTTLES_ID => {
+                Some(DOUBLE_BOTTLES_KEY) => {
                     fire_operation_report.double_bottles =
                         field.as_i64().context("Failed to parse double bottles")?;
                 }
-                DURATION_ID => {
+                Some(DURATION_KEY) => {
                     fire_operation_report.duration =
                         field.as_i64().context("Failed to parse duration")?;
                 }
-                ISSUES_ID => {
+                Some(ISSUES_KEY) => {
                     fire_operation_report.issues =
                         parse_string(field).context("Failed to parse issues")?;
                 }
-                OPERATION_TYPE_ID => {
+                Some(OPERATION_TYPE_KEY) => {
                     fire_operation_report.operation_type =
                         parse_string(field).context("Failed to parse operation type")?;
                 }
-                TYPE_ID => {
+                Some(TYPE_KEY) => {
                     let id = parse_string(field).context("Failed to get type id")?;
-                    fire_operation_report.r#type = Type::new(&id)?;
+                    fire_operation_report.r#type = Type::new(&id, mapping)?;
                 }
-                SINGLE_BOTTLES_ID => {
+                Some(SINGLE_BOTTLES_KEY) => {
                     fire_operation_report.single_bottles =
                         field.as_i64().context("Failed to parse single bottles")?;
                 }
@@ -147,28 +204,67 @@ impl FireOperationReport {
     }
 }
 
+pub fn default_mapping() -> Mapping {
+    Mapping::new(
+        REPORT_TYPE_ID,
+        &[
+            (ACTIVITIES_KEY, ACTIVITIES_ID),
+            (DATE_KEY, DATE_ID),
+            (DOUBLE_BOTTLES_KEY, DOUBLE_BOTTLES_ID),
+            (DURATION_KEY, DURATION_ID),
+            (ISSUES_KEY, ISSUES_ID),
+            (OPERATION_TYPE_KEY, OPERATION_TYPE_ID),
+            (SINGLE_BOTTLES_KEY, SINGLE_BOTTLES_ID),
+            (TYPE_KEY, TYPE_ID),
+        ],
+        &[
+            (
+                ACTIVITIES_KEY,
+                &[
+                    (ACTIVITY_COBRA_KEY, ACTIVITY_COBRA_ID),
+                    (ACTIVITY_FIRE_FIGHTING_KEY, ACTIVITY_FIRE_FIGHTING_ID),
+                    (ACTIVITY_LEADING_KEY, ACTIVITY_LEADING_ID),
+                    (ACTIVITY_RESCUING_KEY, ACTIVITY_RESCUING_ID),
+                    (ACTIVITY_ROOF_OPENING_KEY, ACTIVITY_ROOF_OPENING_ID),
+                    (ACTIVITY_VENTILATION_KEY, ACTIVITY_VENTILATION_ID),
+                ],
+            ),
+            (
+                TYPE_KEY,
+                &[
+                    (TYPE_OPERATION_KEY, TYPE_OPERATION_ID),
+                    (TYPE_TRAINING_KEY, TYPE_TRAINING_ID),
+                ],
+            ),
+        ],
+    )
+}
+
 impl Reports for Vec<FireOperationReport> {
     fn new_from_reports(
         report_type: &response::ReportTypesItem,
         reports: response::Reports,
         users: &HashMap<String, response::Consumer>,
-    ) -> Result<Self>
+        mapping: &Mapping,
+        lenient: bool,
+    ) -> Result<Parsed<Self>>
     where
         Self: Sized,
     {
-        let mut fire_operation_reports: Vec<FireOperationReport> = Vec::default();
-
-        for report in reports.items {
-            let user = users
-                .get(&report.user_cluster_relation_id.to_string())
-                .cloned()
-                .unwrap_or_default();
-            let station_report = FireOperationReport::new_from_report(report_type, &report, &user)
-                .context("Failed to create station report")?;
-            fire_operation_reports.push(station_report);
-        }
+        parse_reports(reports, users, lenient, |report, user| {
+            FireOperationReport::new_from_report(report_type, report, user, mapping)
+                .context("Failed to create fire operation report")
+        })
+    }
+}
+
+impl Export for Vec<FireOperationReport> {
+    fn title(&self) -> String {
+        TITLE.to_string()
+    }
 
-        Ok(fire_operation_reports)
+    fn columns(&self) -> Vec<String> {
+        columns::<FireOperationReport>()
     }
 
     fn print(self) {
@@ -193,10 +289,7 @@ impl Reports for Vec<FireOperationReport> {
         println!("{table}");
     }
 
-    fn write_xlsx(self, path: &Path) -> Result<()> {
-        let mut workbook = Workbook::new();
-        workbook.read_only_recommended();
-
+    fn add_worksheet(self, workbook: &mut Workbook) -> Result<()> {
         let worksheet = workbook.add_worksheet().set_name(TITLE)?;
         set_table(worksheet, &FIRE_OPERATION_REPORTS_HEADERS, self.len())?;
 
@@ -214,16 +307,153 @@ impl Reports for Vec<FireOperationReport> {
             worksheet.write(row, 9, report.double_bottles.to_string())?;
         }
         worksheet.autofit();
-        workbook.save(path)?;
         Ok(())
     }
 }
 
+/// Fire operation reports aggregated per member and year
+#[derive(Clone, Debug, Default)]
+pub struct FireOperationSummary {
+    pub reports: Vec<FireOperationReport>,
+    pub rows: Vec<SummaryRow>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SummaryRow {
+    pub user: String,
+    pub year: i32,
+    pub operations: u32,
+    pub trainings: u32,
+    pub duration: i64,
+    pub single_bottles: i64,
+    pub double_bottles: i64,
+    pub cobra: u32,
+    pub fire_fighting: u32,
+    pub leading: u32,
+    pub rescuing: u32,
+    pub roof_opening: u32,
+    pub ventilation: u32,
+}
+
+impl FireOperationSummary {
+    pub fn new(reports: Vec<FireOperationReport>) -> Self {
+        // Rows are grouped by the member id and sorted by the name
+        let mut rows: BTreeMap<(String, i64, i32), SummaryRow> = BTreeMap::default();
+        for report in &reports {
+            let year = report.date.year();
+            let row = rows
+                .entry((report.user.clone(), report.user_id, year))
+                .or_insert_with(|| SummaryRow {
+                    user: report.user.clone(),
+                    year,
+                    ..Default::default()
+                });
+            row.add(report);
+        }
+
+        Self {
+            reports,
+            rows: rows.into_values().collect(),
+        }
+    }
+}
+
+impl SummaryRow {
+    fn add(&mut self, report: &FireOperationReport) {
+        match report.r#type {
+            Type::Operation => self.operations += 1,
+            Type::Training => self.trainings += 1,
+        }
+        self.duration += report.duration;
+        self.single_bottles += report.single_bottles;
+        self.double_bottles += report.double_bottles;
+        for activity in &report.activities.0 {
+            match activity {
+                Activity::Cobra => self.cobra += 1,
+                Activity::FireFighting => self.fire_fighting += 1,
+                Activity::Leading => self.leading += 1,
+                Activity::Rescuing => self.rescuing += 1,
+                Activity::RoofOpening => self.roof_opening += 1,
+                Activity::Ventilation => self.ventilation += 1,
+            }
+        }
+    }
+
+    fn values(&self) -> [i64; 11] {
+        [
+            self.operations.into(),
+            self.trainings.into(),
+            self.duration,
+            self.single_bottles,
+            self.double_bottles,
+            self.cobra.into(),
+            self.fire_fighting.into(),
+            self.leading.into(),
+            self.rescuing.into(),
+            self.roof_opening.into(),
+            self.ventilation.into(),
+        ]
+    }
+}
+
+impl Serialize for FireOperationSummary {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.rows.serialize(serializer)
+    }
+}
+
+impl Export for FireOperationSummary {
+    fn title(&self) -> String {
+        SUMMARY_TITLE.to_string()
+    }
+
+    fn columns(&self) -> Vec<String> {
+        columns::<SummaryRow>()
+    }
+
+    fn print(self) {
+        let mut table = Table::new();
+        table.set_content_arrangement(ContentArrangement::Dynamic);
+        table.set_header(SUMMARY_HEADERS);
+        for row in self.rows {
+            let mut cells = vec![row.user.clone(), row.year.to_string()];
+            cells.extend(row.values().iter().map(|value| value.to_string()));
+            table.add_row(cells);
+        }
+
+        println!("{table}");
+    }
+
+    /// Adds the reports and the summary as separate worksheets
+    fn add_worksheet(self, workbook: &mut Workbook) -> Result<()> {
+        self.reports.add_worksheet(workbook)?;
+
+        let worksheet = workbook.add_worksheet().set_name(SUMMARY_TITLE)?;
+        set_table(worksheet, &SUMMARY_HEADERS, self.rows.len())?;
+        for (index, summary_row) in self.rows.into_iter().enumerate() {
+            let row = (index + 1) as u32;
+            worksheet.write(row, 0, &summary_row.user)?;
+            worksheet.write(row, 1, summary_row.year)?;
+            for (column, value) in summary_row.values().into_iter().enumerate() {
+                worksheet.write(row, (column + 2) as u16, value)?;
+            }
+        }
+        worksheet.autofit();
+        Ok(())
+    }
+}
+
+impl Dated for Vec<FireOperationReport> {
+    fn retain_range(&mut self, range: &DateRange) {
+        self.retain(|report| range.contains(report.date));
+    }
+}
+
 impl Type {
-    pub fn new(id: &str) -> Result<Self> {
-        let variant = match id {
-            TYPE_OPERATION_ID => Self::Operation,
-            TYPE_TRAINING_ID => Self::Training,
+    pub fn new(id: &str, mapping: &Mapping) -> Result<Self> {
+        let variant = match mapping.option(TYPE_KEY, id) {
+            Some(TYPE_OPERATION_KEY) => Self::Operation,
+            Some(TYPE_TRAINING_KEY) => Self::Training,
             _ => bail!("Unknow type variant \"{}\"", id),
         };
 
@@ -241,14 +471,16 @@ impl Display for Type {
 }
 
 impl Activities {
-    pub fn new(value: &Value) -> Result<Activities> {
+    pub fn new(value: &Value, mapping: &Mapping) -> Result<Activities> {
         let activities: Vec<Activity> = value
             .as_array()
             .context("Failed to parse array")?
             .iter()
-            .map(|value| parse_string(value).expect("Failed to parse array element as string"))
-            .map(|value| Activity::new(&value).expect("Failed to parse activites"))
-            .collect();
+            .map(|value| {
+                let id = parse_string(value).context("Failed to parse activity id")?;
+                Activity::new(&id, mapping).context("Failed to parse activity")
+            })
+            .collect::<Result<Vec<Activity>>>()?;
         Ok(Activities(activities))
     }
 }
@@ -267,14 +499,14 @@ impl Display for Activities {
 }
 
 impl Activity {
-    pub fn new(id: &str) -> Result<Self> {
-        let variant = match id {
-            ACTIVITY_COBRA_ID => Self::Cobra,
-            ACTIVITY_FIRE_FIGHTING_ID => Self::FireFighting,
-            ACTIVITY_LEADING_ID => Self::Leading,
-            ACTIVITY_RESCUING_ID => Self::Rescuing,
-            ACTIVITY_ROOF_OPENING_ID => Self::RoofOpening,
-            ACTIVITY_VENTILATION_ID => Self::Ventilation,
+    pub fn new(id: &str, mapping: &Mapping) -> Result<Self> {
+        let variant = match mapping.option(ACTIVITIES_KEY, id) {
+            Some(ACTIVITY_COBRA_KEY) => Self::Cobra,
+            Some(ACTIVITY_FIRE_FIGHTING_KEY) => Self::FireFighting,
+            Some(ACTIVITY_LEADING_KEY) => Self::Leading,
+            Some(ACTIVITY_RESCUING_KEY) => Self::Rescuing,
+            Some(ACTIVITY_ROOF_OPENING_KEY) => Self::RoofOpening,
+            Some(ACTIVITY_VENTILATION_KEY) => Self::Ventilation,
             _ => bail!("Unknow activity variant \"{}\"", id),
         };
 
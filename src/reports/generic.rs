@@ -0,0 +1,350 @@
+use std::{collections::HashMap, fmt::Display};
+
+use anyhow::{anyhow, Context, Result};
+use chrono::NaiveDate;
+use comfy_table::{ContentArrangement, Table};
+use rust_xlsxwriter::{Format, Workbook};
+use serde::{
+    ser::{SerializeMap, SerializeSeq},
+    Serialize, Serializer,
+};
+use serde_json::Value;
+
+use super::{parse_date, parse_reports, parse_string, set_table, Export, Parsed, Reports};
+use crate::{
+    config::Mapping,
+    divera::schema::response::{self, ReportTypesItemFields, ReportTypesItemFieldsType},
+};
+
+const ID_KEY: &str = "id";
+const USER_KEY: &str = "user";
+const ID_TEXT: &str = "ID";
+const USER_TEXT: &str = "Mitglied";
+const CHECKED_TEXT: &str = "Ja";
+const UNCHECKED_TEXT: &str = "Nein";
+
+/// Characters which are not allowed in xlsx worksheet names
+const INVALID_SHEET_NAME_CHARS: [char; 7] = ['[', ']', ':', '*', '?', '/', '\\'];
+const MAX_SHEET_NAME_LENGTH: usize = 31;
+
+/// Reports of an arbitrary report type.
+/// The columns are built from the fields of the report type.
+#[derive(Clone, Debug, Default)]
+pub struct GenericReports {
+    pub title: String,
+    pub fields: Vec<ReportTypesItemFields>,
+    pub reports: Vec<GenericReport>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct GenericReport {
+    pub id: i64,
+    pub user: String,
+    pub values: Vec<FieldValue>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub enum FieldValue {
+    #[default]
+    Empty,
+    Text(String),
+    Number(f64),
+    Date(NaiveDate),
+    Options(Vec<String>),
+}
+
+impl GenericReport {
+    pub fn new_from_report(
+        report_type: &response::ReportTypesItem,
+        report: &response::Report,
+        user: &response::Consumer,
+    ) -> Result<Self> {
+        let mut values = Vec::with_capacity(report_type.fields.len());
+        // Reports created before a field was added have no value for it
+        for (index, field_type) in report_type.fields.iter().enumerate() {
+            let value = match report.fields.get(index) {
+                Some(field) => FieldValue::new(field, field_type)
+                    .with_context(|| format!("Failed to parse field \"{}\"", field_type.name))?,
+                None => FieldValue::Empty,
+            };
+            values.push(value);
+        }
+
+        Ok(GenericReport {
+            id: report.id,
+            user: user.stdformat_name.clone(),
+            values,
+        })
+    }
+}
+
+impl Reports for GenericReports {
+    fn new_from_reports(
+        report_type: &response::ReportTypesItem,
+        reports: response::Reports,
+        users: &HashMap<String, response::Consumer>,
+        _mapping: &Mapping,
+        lenient: bool,
+    ) -> Result<Parsed<Self>>
+    where
+        Self: Sized,
+    {
+        let parsed = parse_reports(reports, users, lenient, |report, user| {
+            GenericReport::new_from_report(report_type, report, user)
+        })?;
+
+        Ok(parsed.map(|reports| GenericReports {
+            title: report_type.name.clone(),
+            fields: report_type.fields.clone(),
+            reports,
+        }))
+    }
+}
+
+impl Export for GenericReports {
+    fn title(&self) -> String {
+        sheet_name(&self.title)
+    }
+
+    /// Same keys as the serialized records of the reports
+    fn columns(&self) -> Vec<String> {
+        let mut columns = vec![ID_KEY.to_string(), USER_KEY.to_string()];
+        columns.extend(self.field_names());
+        columns
+    }
+
+    fn print(self) {
+        let mut table = Table::new();
+        table.set_content_arrangement(ContentArrangement::Dynamic);
+        table.set_header(self.headers());
+        for report in self.reports {
+            let mut row = vec![report.id.to_string(), report.user];
+            row.extend(report.values.iter().map(|value| value.to_string()));
+            table.add_row(row);
+        }
+
+        println!("{table}");
+    }
+
+    fn add_worksheet(self, workbook: &mut Workbook) -> Result<()> {
+        let headers = self.headers();
+        let headers: Vec<&str> = headers.iter().map(String::as_str).collect();
+        let name = unique_sheet_name(workbook, &self.title);
+        let worksheet = workbook.add_worksheet().set_name(name)?;
+        set_table(worksheet, &headers, self.reports.len())?;
+
+        let date_format = Format::new().set_num_format("dd.mm.yyyy");
+        for (index, report) in self.reports.into_iter().enumerate() {
+            let row = (index + 1) as u32;
+            worksheet.write(row, 0, report.id)?;
+            worksheet.write(row, 1, report.user)?;
+            for (column, value) in report.values.into_iter().enumerate() {
+                let column = (column + 2) as u16;
+                match value {
+                    FieldValue::Empty => {}
+                    FieldValue::Text(text) => {
+                        worksheet.write(row, column, text)?;
+                    }
+                    FieldValue::Number(number) => {
+                        worksheet.write(row, column, number)?;
+                    }
+                    FieldValue::Date(date) => {
+                        worksheet.write_datetime_with_format(row, column, date, &date_format)?;
+                    }
+                    options @ FieldValue::Options(_) => {
+                        worksheet.write(row, column, options.to_string())?;
+                    }
+                };
+            }
+        }
+        worksheet.autofit();
+        Ok(())
+    }
+}
+
+impl GenericReports {
+    fn headers(&self) -> Vec<String> {
+        let mut headers = vec![ID_TEXT.to_string(), USER_TEXT.to_string()];
+        headers.extend(self.field_names());
+        headers
+    }
+
+    /// Names of the fields, with the field id appended to names which are not unique
+    fn field_names(&self) -> Vec<String> {
+        let mut counts: HashMap<&str, usize> = [ID_KEY, USER_KEY, ID_TEXT, USER_TEXT]
+            .into_iter()
+            .map(|name| (name, 1))
+            .collect();
+        for field in &self.fields {
+            *counts.entry(field.name.as_str()).or_default() += 1;
+        }
+        self.fields
+            .iter()
+            .map(|field| match counts[field.name.as_str()] {
+                1 => field.name.clone(),
+                _ => format!("{} ({})", field.name, field.id),
+            })
+            .collect()
+    }
+}
+
+impl Serialize for GenericReports {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut reports = serializer.serialize_seq(Some(self.reports.len()))?;
+        let names = self.field_names();
+        for report in &self.reports {
+            reports.serialize_element(&GenericRecord {
+                names: &names,
+                report,
+            })?;
+        }
+        reports.end()
+    }
+}
+
+/// A report serialized as map of the unique field names to their values.
+/// Every field is part of the record, missing values are null.
+struct GenericRecord<'a> {
+    names: &'a [String],
+    report: &'a GenericReport,
+}
+
+impl Serialize for GenericRecord<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut record = serializer.serialize_map(Some(self.names.len() + 2))?;
+        record.serialize_entry(ID_KEY, &self.report.id)?;
+        record.serialize_entry(USER_KEY, &self.report.user)?;
+        for (index, name) in self.names.iter().enumerate() {
+            record.serialize_entry(name, &self.report.values.get(index))?;
+        }
+        record.end()
+    }
+}
+
+impl Serialize for FieldValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            FieldValue::Empty => serializer.serialize_none(),
+            FieldValue::Text(text) => serializer.serialize_str(text),
+            FieldValue::Number(number) => serializer.serialize_f64(*number),
+            FieldValue::Date(date) => date.serialize(serializer),
+            FieldValue::Options(options) => options.serialize(serializer),
+        }
+    }
+}
+
+impl FieldValue {
+    pub fn new(value: &Value, field_type: &ReportTypesItemFields) -> Result<Self> {
+        if is_empty(value) {
+            return Ok(Self::Empty);
+        }
+
+        let field_value = match field_type.r#type {
+            ReportTypesItemFieldsType::Date => Self::Date(parse_date(value)?),
+            ReportTypesItemFieldsType::Number => Self::Number(parse_number(value)?),
+            ReportTypesItemFieldsType::String
+            | ReportTypesItemFieldsType::TextArea
+            | ReportTypesItemFieldsType::TextInput => Self::Text(parse_string(value)?),
+            ReportTypesItemFieldsType::Radio | ReportTypesItemFieldsType::SelectBox => {
+                let id = parse_string(value)?;
+                Self::Options(vec![option_name(&id, field_type)])
+            }
+            ReportTypesItemFieldsType::Checkbox => match value {
+                Value::Bool(true) => Self::Text(CHECKED_TEXT.to_string()),
+                Value::Bool(false) => Self::Text(UNCHECKED_TEXT.to_string()),
+                Value::Array(ids) => Self::Options(
+                    ids.iter()
+                        .map(|id| Ok(option_name(&parse_string(id)?, field_type)))
+                        .collect::<Result<Vec<String>>>()?,
+                ),
+                _ => Self::Options(vec![option_name(&parse_string(value)?, field_type)]),
+            },
+        };
+
+        Ok(field_value)
+    }
+}
+
+impl Display for FieldValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldValue::Empty => Ok(()),
+            FieldValue::Text(text) => f.write_str(text),
+            FieldValue::Number(number) => write!(f, "{number}"),
+            FieldValue::Date(date) => write!(f, "{date}"),
+            FieldValue::Options(options) => f.write_str(&options.join(", ")),
+        }
+    }
+}
+
+/// Resolves the id of an option to its display name.
+/// Unknown ids are kept as they are, since divera does not remove options of old reports.
+fn option_name(id: &str, field_type: &ReportTypesItemFields) -> String {
+    field_type
+        .options
+        .iter()
+        .flatten()
+        .find(|option| option.id == id)
+        .map(|option| option.name.clone())
+        .unwrap_or_else(|| {
+            log::warn!("Unknown option \"{}\" of field \"{}\"", id, field_type.name);
+            id.to_string()
+        })
+}
+
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::String(string) => string.is_empty(),
+        Value::Array(array) => array.is_empty(),
+        _ => false,
+    }
+}
+
+fn parse_number(value: &Value) -> Result<f64> {
+    if let Some(number) = value.as_f64() {
+        return Ok(number);
+    }
+
+    value
+        .as_str()
+        .ok_or_else(|| anyhow!("Value not a number"))?
+        .parse::<f64>()
+        .context("Failed to parse number")
+}
+
+/// Worksheet name of the title which is not used by the workbook yet.
+/// Titles which are equal after shortening are numbered.
+pub(super) fn unique_sheet_name(workbook: &mut Workbook, title: &str) -> String {
+    let taken: Vec<String> = workbook
+        .worksheets()
+        .iter()
+        .map(|worksheet| worksheet.name().to_lowercase())
+        .collect();
+    let name = sheet_name(title);
+    let mut unique = name.clone();
+    let mut number = 1;
+    // Worksheet names are compared case-insensitively
+    while taken.contains(&unique.to_lowercase()) {
+        number += 1;
+        let suffix = format!(" {number}");
+        let length = MAX_SHEET_NAME_LENGTH - suffix.len();
+        unique = name.chars().take(length).collect::<String>() + &suffix;
+    }
+    unique
+}
+
+/// Replaces invalid characters and shortens the title to a valid worksheet name
+pub(super) fn sheet_name(title: &str) -> String {
+    title
+        .chars()
+        .map(|char| {
+            if INVALID_SHEET_NAME_CHARS.contains(&char) || char.is_control() {
+                '_'
+            } else {
+                char
+            }
+        })
+        .take(MAX_SHEET_NAME_LENGTH)
+        .collect()
+}
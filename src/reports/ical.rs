@@ -0,0 +1,97 @@
+use std::io::Write;
+
+use anyhow::Result;
+use chrono::{NaiveDate, Utc};
+
+const PRODUCT_ID: &str = "-//divera-reports//DE";
+const MAX_LINE_LENGTH: usize = 75;
+
+/// Writer for iCalendar (RFC 5545) files
+pub struct Calendar<W: Write> {
+    writer: W,
+    timestamp: String,
+}
+
+/// All-day event, `end` is the last day of the event
+pub struct Event<'a> {
+    pub uid: &'a str,
+    pub begin: NaiveDate,
+    pub end: NaiveDate,
+    pub summary: &'a str,
+    pub description: &'a str,
+}
+
+impl<W: Write> Calendar<W> {
+    pub fn new(mut writer: W, name: &str) -> Result<Self> {
+        write_line(&mut writer, "BEGIN:VCALENDAR")?;
+        write_line(&mut writer, "VERSION:2.0")?;
+        write_line(&mut writer, &format!("PRODID:{PRODUCT_ID}"))?;
+        write_line(&mut writer, "CALSCALE:GREGORIAN")?;
+        write_line(&mut writer, &format!("X-WR-CALNAME:{}", escape(name)))?;
+
+        Ok(Self {
+            writer,
+            timestamp: Utc::now().format("%Y%m%dT%H%M%SZ").to_string(),
+        })
+    }
+
+    pub fn add_event(&mut self, event: &Event) -> Result<()> {
+        // The end date of all-day events is exclusive
+        let end = event.end.succ_opt().unwrap_or(event.end);
+
+        write_line(&mut self.writer, "BEGIN:VEVENT")?;
+        write_line(&mut self.writer, &format!("UID:{}", escape(event.uid)))?;
+        write_line(&mut self.writer, &format!("DTSTAMP:{}", self.timestamp))?;
+        write_line(
+            &mut self.writer,
+            &format!("DTSTART;VALUE=DATE:{}", event.begin.format("%Y%m%d")),
+        )?;
+        write_line(
+            &mut self.writer,
+            &format!("DTEND;VALUE=DATE:{}", end.format("%Y%m%d")),
+        )?;
+        write_line(
+            &mut self.writer,
+            &format!("SUMMARY:{}", escape(event.summary)),
+        )?;
+        if !event.description.is_empty() {
+            write_line(
+                &mut self.writer,
+                &format!("DESCRIPTION:{}", escape(event.description)),
+            )?;
+        }
+        write_line(&mut self.writer, "TRANSP:TRANSPARENT")?;
+        write_line(&mut self.writer, "END:VEVENT")?;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        write_line(&mut self.writer, "END:VCALENDAR")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Writes a content line folded after 75 octets without splitting characters
+fn write_line(writer: &mut impl Write, line: &str) -> Result<()> {
+    let mut length = 0;
+    for char in line.chars() {
+        if length + char.len_utf8() > MAX_LINE_LENGTH {
+            writer.write_all(b"\r\n ")?;
+            // The leading space of the continuation line counts to its length
+            length = 1;
+        }
+        write!(writer, "{char}")?;
+        length += char.len_utf8();
+    }
+    writer.write_all(b"\r\n")?;
+    Ok(())
+}
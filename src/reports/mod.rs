@@ -1,47 +1,353 @@
 pub mod absent;
+#[cfg(feature = "async")]
+pub mod all;
+pub mod compliance;
+pub mod coverage;
+pub mod file_name;
 pub mod fire_operation;
+pub mod generic;
+pub mod ical;
 pub mod roster;
 pub mod station;
 
-use anyhow::{anyhow, Context, Result};
-use chrono::{DateTime, NaiveDate};
-use rust_xlsxwriter::{Format, TableColumn, Worksheet};
-use rustydav::client::Client;
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Datelike, Months, NaiveDate};
+use rust_xlsxwriter::{Format, TableColumn, Workbook, Worksheet};
+use serde::Serialize;
 use serde_json::Value;
-use std::{collections::HashMap, fs::File, path::Path};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+};
 use tempfile::tempdir;
 
 use crate::{
-    config::WebDav,
+    config::Mapping,
     divera::schema::response::{self},
+    upload::{fingerprints::fingerprint, Destinations, Summary},
 };
 
-pub trait Reports {
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Xlsx,
+    Csv,
+    Json,
+    /// Newline delimited json with one report per line
+    NdJson,
+    /// iCalendar with one event per report
+    Ics,
+}
+
+const ERRORS_TITLE: &str = "Fehler";
+const ERRORS_HEADERS: [&str; 3] = ["ID", "Mitglied", ERRORS_TITLE];
+
+pub trait Reports: Export {
+    /// Parses the reports. In lenient mode malformed reports are skipped and returned as errors
     fn new_from_reports(
         report_type: &response::ReportTypesItem,
         reports: response::Reports,
         users: &HashMap<String, response::Consumer>,
-    ) -> Result<Self>
+        mapping: &Mapping,
+        lenient: bool,
+    ) -> Result<Parsed<Self>>
     where
         Self: Sized;
+}
+
+/// Report which could not be parsed
+#[derive(Clone, Debug, Serialize)]
+pub struct ReportError {
+    pub id: i64,
+    pub user: String,
+    pub reason: String,
+}
+
+/// Parsed reports together with the reports which were skipped
+#[derive(Clone, Debug, Default)]
+pub struct Parsed<T> {
+    pub reports: T,
+    pub errors: Vec<ReportError>,
+}
+
+impl<T> Parsed<T> {
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Parsed<U> {
+        Parsed {
+            reports: f(self.reports),
+            errors: self.errors,
+        }
+    }
+
+    pub fn try_map<U>(self, f: impl FnOnce(T) -> Result<U>) -> Result<Parsed<U>> {
+        Ok(Parsed {
+            reports: f(self.reports)?,
+            errors: self.errors,
+        })
+    }
+}
+
+/// Only the reports are serialized, the errors are logged
+impl<T: Serialize> Serialize for Parsed<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.reports.serialize(serializer)
+    }
+}
+
+impl<T: Export> Export for Parsed<T> {
+    fn title(&self) -> String {
+        self.reports.title()
+    }
+
+    fn columns(&self) -> Vec<String> {
+        self.reports.columns()
+    }
+
+    fn print(self) {
+        self.reports.print();
+        if self.errors.is_empty() {
+            return;
+        }
+
+        let mut table = comfy_table::Table::new();
+        table.set_content_arrangement(comfy_table::ContentArrangement::Dynamic);
+        table.set_header(ERRORS_HEADERS);
+        for error in self.errors {
+            table.add_row(vec![error.id.to_string(), error.user, error.reason]);
+        }
+        println!("{ERRORS_TITLE}");
+        println!("{table}");
+    }
+
+    /// Skipped reports are part of the exported file and thus of the fingerprint
+    fn fingerprint(&self) -> Result<String> {
+        fingerprint(&(self.reports.fingerprint()?, &self.errors))
+    }
+
+    /// Adds a worksheet with the skipped reports next to the reports
+    fn add_worksheet(self, workbook: &mut Workbook) -> Result<()> {
+        self.reports.add_worksheet(workbook)?;
+        if self.errors.is_empty() {
+            return Ok(());
+        }
+
+        // Named after the worksheet of the reports, which may have been numbered
+        let title = workbook
+            .worksheets()
+            .last()
+            .map(Worksheet::name)
+            .unwrap_or_default();
+        let name = generic::unique_sheet_name(workbook, &format!("{ERRORS_TITLE} {title}"));
+        let worksheet = workbook.add_worksheet().set_name(name)?;
+        set_table(worksheet, &ERRORS_HEADERS, self.errors.len())?;
+        for (index, error) in self.errors.into_iter().enumerate() {
+            let row = (index + 1) as u32;
+            worksheet.write(row, 0, error.id)?;
+            worksheet.write(row, 1, error.user)?;
+            worksheet.write(row, 2, error.reason)?;
+        }
+        worksheet.autofit();
+        Ok(())
+    }
+
+    fn write_ics(&self, writer: impl Write) -> Result<()> {
+        self.reports.write_ics(writer)
+    }
+}
+
+impl<T: Dated> Dated for Parsed<T> {
+    fn retain_range(&mut self, range: &DateRange) {
+        self.reports.retain_range(range);
+    }
+}
+
+/// Parses each report with the given function.
+/// In lenient mode a failed report is logged and collected instead of aborting.
+fn parse_reports<T>(
+    reports: response::Reports,
+    users: &HashMap<String, response::Consumer>,
+    lenient: bool,
+    parse: impl Fn(&response::Report, &response::Consumer) -> Result<T>,
+) -> Result<Parsed<Vec<T>>> {
+    let mut parsed = Parsed {
+        reports: Vec::default(),
+        errors: Vec::default(),
+    };
+    for report in reports.items {
+        let user = users
+            .get(&report.user_cluster_relation_id.to_string())
+            .cloned()
+            .unwrap_or_default();
+        match parse(&report, &user) {
+            Ok(parsed_report) => parsed.reports.push(parsed_report),
+            Err(error) if lenient => {
+                log::warn!("Skipping report {}: {error:#}", report.id);
+                parsed.errors.push(ReportError {
+                    id: report.id,
+                    user: user.stdformat_name,
+                    reason: format!("{error:#}"),
+                });
+            }
+            Err(error) => {
+                return Err(error.context(format!("Failed to parse report {}", report.id)))
+            }
+        }
+    }
+
+    Ok(parsed)
+}
+
+/// Output of reports and analyses
+pub trait Export: Serialize {
+    fn title(&self) -> String;
+    /// Keys of the serialized reports, which are the csv header even without reports
+    fn columns(&self) -> Vec<String>;
     fn print(self);
-    fn write_xlsx(self, path: &Path) -> Result<()>;
-    fn upload(self, file_name: &str, config: WebDav) -> Result<()>
+    /// Adds the reports as worksheet named by the title to the workbook
+    fn add_worksheet(self, workbook: &mut Workbook) -> Result<()>;
+    fn write_xlsx(self, path: &Path) -> Result<()>
+    where
+        Self: Sized,
+    {
+        let mut workbook = Workbook::new();
+        workbook.read_only_recommended();
+        self.add_worksheet(&mut workbook)?;
+        workbook.save(path)?;
+        Ok(())
+    }
+    /// Writes the reports in a text format with typed values
+    fn write_text(&self, writer: impl Write, format: OutputFormat) -> Result<()> {
+        match format {
+            OutputFormat::Xlsx => bail!("Xlsx is not a text format"),
+            OutputFormat::Csv => write_csv(self, &self.columns(), writer),
+            OutputFormat::Json => write_json(self, writer),
+            OutputFormat::NdJson => write_ndjson(self, writer),
+            OutputFormat::Ics => self.write_ics(writer),
+        }
+    }
+    fn write_ics(&self, _writer: impl Write) -> Result<()> {
+        bail!("{} can not be written as iCalendar", self.title())
+    }
+    fn write(self, path: &Path, format: OutputFormat) -> Result<()>
+    where
+        Self: Sized,
+    {
+        match format {
+            OutputFormat::Xlsx => self.write_xlsx(path),
+            _ => {
+                let file = File::create(path)
+                    .with_context(|| format!("Failed to create {}", path.display()))?;
+                self.write_text(BufWriter::new(file), format)
+            }
+        }
+    }
+    /// Hash of the content which changes whenever the exported file changes, apart from timestamps
+    fn fingerprint(&self) -> Result<String> {
+        fingerprint(&(self.title(), self))
+    }
+    /// Fingerprint of the file exported in the format
+    fn file_fingerprint(&self, format: OutputFormat) -> Result<String> {
+        fingerprint(&(self.fingerprint()?, format!("{format:?}")))
+    }
+    /// One line per report with its non-empty fields, used to list new reports in mails
+    fn summary(&self) -> Result<Summary> {
+        let entries = records(self)?
+            .iter()
+            .map(|record| match record {
+                Value::Object(fields) => fields
+                    .values()
+                    .map(csv_field)
+                    .filter(|field| !field.is_empty())
+                    .collect::<Vec<String>>()
+                    .join(", "),
+                value => csv_field(value),
+            })
+            .collect();
+        Ok(Summary {
+            title: self.title(),
+            entries,
+        })
+    }
+    /// Writes the reports into a temporary file which is uploaded to the destinations
+    fn upload(
+        self,
+        file_name: &str,
+        format: OutputFormat,
+        destinations: &Destinations,
+    ) -> Result<()>
     where
         Self: Sized,
     {
+        let fingerprint = self.file_fingerprint(format)?;
+        let summary = self.summary()?;
         let temp_dir = tempdir().context("Failed to create temp dir")?;
         let file_path = temp_dir.path().join(file_name);
-        self.write_xlsx(&file_path)
-            .context("Failed to write reports to xlsx")?;
-
-        let webdav_client = Client::init(&config.username, &config.password);
-        let path = config.root_directory + "/" + file_name;
-        let file = File::open(file_path)?;
-        webdav_client
-            .put(file, &path)
-            .context("Failed to upload reports")?;
-        Ok(())
+        if let Some(parent) = file_path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        self.write(&file_path, format)
+            .context("Failed to write reports")?;
+
+        destinations.upload(&file_path, file_name, &fingerprint, &summary)
+    }
+}
+
+/// Reports which have a primary date and can be filtered by it
+pub trait Dated {
+    fn retain_range(&mut self, range: &DateRange);
+}
+
+/// Inclusive range of dates, open if a bound is missing
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DateRange {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl DateRange {
+    pub fn new(from: Option<NaiveDate>, to: Option<NaiveDate>) -> Result<Self> {
+        if let (Some(from), Some(to)) = (from, to) {
+            if from > to {
+                bail!("Start date {from} is after end date {to}");
+            }
+        }
+        Ok(Self { from, to })
+    }
+
+    pub fn month(year: i32, month: u32) -> Result<Self> {
+        let from = NaiveDate::from_ymd_opt(year, month, 1)
+            .ok_or_else(|| anyhow!("Invalid month {year}-{month}"))?;
+        let to = from
+            .checked_add_months(Months::new(1))
+            .and_then(|next_month| next_month.pred_opt())
+            .ok_or_else(|| anyhow!("Invalid month {year}-{month}"))?;
+        Self::new(Some(from), Some(to))
+    }
+
+    /// The month before the month of the given date
+    pub fn previous_month(date: NaiveDate) -> Result<Self> {
+        let date = date
+            .checked_sub_months(Months::new(1))
+            .ok_or_else(|| anyhow!("Invalid date {date}"))?;
+        Self::month(date.year(), date.month())
+    }
+
+    pub fn year(year: i32) -> Result<Self> {
+        let from =
+            NaiveDate::from_ymd_opt(year, 1, 1).ok_or_else(|| anyhow!("Invalid year {year}"))?;
+        let to =
+            NaiveDate::from_ymd_opt(year, 12, 31).ok_or_else(|| anyhow!("Invalid year {year}"))?;
+        Self::new(Some(from), Some(to))
+    }
+
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.overlaps(date, date)
+    }
+
+    pub fn overlaps(&self, begin: NaiveDate, end: NaiveDate) -> bool {
+        self.from.is_none_or(|from| end >= from) && self.to.is_none_or(|to| begin <= to)
     }
 }
 
@@ -55,10 +361,84 @@ impl Default for response::Consumer {
     }
 }
 
+fn write_json(reports: &(impl Serialize + ?Sized), mut writer: impl Write) -> Result<()> {
+    serde_json::to_writer_pretty(&mut writer, reports).context("Failed to write json")?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+fn write_ndjson(reports: &(impl Serialize + ?Sized), mut writer: impl Write) -> Result<()> {
+    for record in records(reports)? {
+        serde_json::to_writer(&mut writer, &record).context("Failed to write json")?;
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+fn write_csv(
+    reports: &(impl Serialize + ?Sized),
+    columns: &[String],
+    writer: impl Write,
+) -> Result<()> {
+    let records = records(reports)?;
+    let mut writer = csv::Writer::from_writer(writer);
+
+    match records.first() {
+        Some(Value::Object(first)) => writer.write_record(first.keys())?,
+        _ => writer.write_record(columns)?,
+    }
+    for record in records {
+        let Value::Object(record) = record else {
+            bail!("Report is not an object");
+        };
+        writer.write_record(record.values().map(csv_field))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Keys of a serialized report, taken from its default
+fn columns<T: Serialize + Default>() -> Vec<String> {
+    match serde_json::to_value(T::default()) {
+        Ok(Value::Object(fields)) => fields.keys().cloned().collect(),
+        _ => Vec::default(),
+    }
+}
+
+/// Serializes the reports into a list of json values
+fn records(reports: &(impl Serialize + ?Sized)) -> Result<Vec<Value>> {
+    match serde_json::to_value(reports).context("Failed to serialize reports")? {
+        Value::Array(records) => Ok(records),
+        _ => bail!("Reports are not a list"),
+    }
+}
+
+fn csv_field(value: &Value) -> String {
+    match value {
+        Value::Null => String::default(),
+        Value::String(string) => string.clone(),
+        Value::Array(values) => values
+            .iter()
+            .map(csv_field)
+            .collect::<Vec<String>>()
+            .join(", "),
+        value => value.to_string(),
+    }
+}
+
+/// Adds a table with the headers in the first row and `table_length` rows below
 fn set_table(worksheet: &mut Worksheet, headers: &[&str], table_length: usize) -> Result<()> {
     let mut table_headers = Vec::default();
     let format = Format::new().set_bold();
 
+    // A table needs at least one data row, so empty reports only get the headers
+    if table_length == 0 {
+        for (column, header) in headers.iter().enumerate() {
+            worksheet.write_with_format(0, column as u16, *header, &format)?;
+        }
+        return Ok(());
+    }
+
     for header in headers {
         table_headers.push(
             TableColumn::new()
@@ -68,13 +448,7 @@ fn set_table(worksheet: &mut Worksheet, headers: &[&str], table_length: usize) -
     }
     let table = rust_xlsxwriter::Table::new().set_columns(&table_headers);
     worksheet
-        .add_table(
-            0,
-            0,
-            table_length as u32 - 1,
-            headers.len() as u16 - 1,
-            &table,
-        )
+        .add_table(0, 0, table_length as u32, headers.len() as u16 - 1, &table)
         .context("Failed to create table")?;
     Ok(())
 }
@@ -101,7 +475,7 @@ fn parse_date(value: &Value) -> Result<NaiveDate> {
             .parse::<f64>()?
             .trunc() as i64
     };
-    let datetime = DateTime::from_timestamp(timestamp as i64, 0)
+    let datetime = DateTime::from_timestamp(timestamp, 0)
         .ok_or_else(|| anyhow!("Failed to parse \"{}\" to datetime", timestamp))?
         .naive_utc()
         .date();
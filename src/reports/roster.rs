@@ -1,11 +1,13 @@
 use std::{collections::HashMap, fmt::Display};
 
-use super::{parse_string, set_table, Reports};
-use crate::divera::schema::response;
+use super::{columns, parse_reports, parse_string, set_table, Export, Parsed, Reports};
+use crate::{config::Mapping, divera::schema::response};
 use anyhow::{bail, Context, Result};
 use comfy_table::{ContentArrangement, Table};
 use rust_xlsxwriter::Workbook;
+use serde::Serialize;
 
+const REPORT_TYPE_ID: i64 = 12112;
 const DESCRIPTION_ID: &str = "2cefd98b-9ea5-4329-b657-7a2a74483c51";
 const PARTICIPATION_HELPING_ID: &str = "57e60afd-be43-48b2-ba73-d092f999b91c";
 const PARTICIPATION_ID: &str = "d8049a3a-407c-480f-93f8-6736a27e9d6e";
@@ -21,6 +23,21 @@ const TYPE_EVENT_ID: &str = "36f1d684-4e00-4fbf-8e3a-ff5a9bf2e931";
 const TYPE_ID: &str = "ab71921a-70b5-46de-b198-e342c50fe262";
 const TYPE_TRAINING_ID: &str = "1cfa8920-be7b-4e2b-be6b-4ba16bde6aa5";
 
+const DESCRIPTION_KEY: &str = "description";
+const PARTICIPATION_HELPING_KEY: &str = "helping";
+const PARTICIPATION_KEY: &str = "participation";
+const PARTICIPATION_RESPONSIBLE_KEY: &str = "responsible";
+const POTENTIAL_DATE_KEY: &str = "potential_date";
+const TIMESCOPE_BOTH_KEY: &str = "both";
+const TIMESCOPE_FULL_KEY: &str = "full";
+const TIMESCOPE_HALF_KEY: &str = "half";
+const TIMESCOPE_KEY: &str = "time_scope";
+const TIMESCOPE_OTHER_KEY: &str = "other";
+const TOPIC_KEY: &str = "topic";
+const TYPE_EVENT_KEY: &str = "event";
+const TYPE_KEY: &str = "type";
+const TYPE_TRAINING_KEY: &str = "training";
+
 const TITLE: &str = "Vorschläge_Dienstplan";
 const DESCRIPTION_TEXT: &str = "Beschreibung";
 const PARTICIPATION_HELPING_TEXT: &str = "Helfend";
@@ -48,7 +65,7 @@ const ROSTER_REPORTS_HEADERS: [&str; 8] = [
     DESCRIPTION_TEXT,
 ];
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize)]
 pub struct RosterReport {
     pub id: i64,
     pub user: String,
@@ -60,19 +77,22 @@ pub struct RosterReport {
     pub potential_date: String,
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Type {
     #[default]
     Training,
     Event,
 }
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Participation {
     #[default]
     Responsible,
     Helping,
 }
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum TimeScope {
     #[default]
     Half,
@@ -86,6 +106,7 @@ impl RosterReport {
         report_type: &response::ReportTypesItem,
         report: &response::Report,
         user: &response::Consumer,
+        mapping: &Mapping,
     ) -> Result<Self> {
         let mut roster_report = RosterReport {
             id: report.id,
@@ -94,38 +115,41 @@ impl RosterReport {
         };
 
         for (field, field_type) in report.fields.iter().zip(report_type.fields.iter()) {
-            match field_type.id.as_str() {
-                DESCRIPTION_ID => {
+            match mapping.field(&field_type.id) {
+                Some(DESCRIPTION_KEY) => {
                     roster_report.description =
                         parse_string(field).context("Failed to parse description")?;
                 }
-                PARTICIPATION_ID => {
+                Some(PARTICIPATION_KEY) => {
                     let id = parse_string(field).context("Failed to get participation id")?;
-                    if id.is_empty() {}
                     roster_report.participation = if id.is_empty() {
                         None
                     } else {
-                        Some(Participation::new(&id).context("Failed to create participation")?)
+                        Some(
+                            Participation::new(&id, mapping)
+                                .context("Failed to create participation")?,
+                        )
                     }
                 }
-                POTENTIAL_DATE_ID => {
+                Some(POTENTIAL_DATE_KEY) => {
                     roster_report.potential_date =
                         parse_string(field).context("Failed to get potential date")?;
                 }
-                TIMESCOPE_ID => {
+                Some(TIMESCOPE_KEY) => {
                     let id = parse_string(field).context("Failed to get timescope id")?;
                     roster_report.time_scope = if id.is_empty() {
                         None
                     } else {
-                        Some(TimeScope::new(&id).context("Failed to create timescope")?)
+                        Some(TimeScope::new(&id, mapping).context("Failed to create timescope")?)
                     }
                 }
-                TOPIC_ID => {
+                Some(TOPIC_KEY) => {
                     roster_report.topic = parse_string(field).context("Failed to get topic")?;
                 }
-                TYPE_ID => {
+                Some(TYPE_KEY) => {
                     let id = parse_string(field).context("Failed to get type id")?;
-                    roster_report.r#type = Type::new(&id).context("Failed to create type")?;
+                    roster_report.r#type =
+                        Type::new(&id, mapping).context("Failed to create type")?;
                 }
                 _ => bail!("Unknown roster report type id \"{}\"", field_type.name),
             };
@@ -134,24 +158,69 @@ impl RosterReport {
     }
 }
 
+pub fn default_mapping() -> Mapping {
+    Mapping::new(
+        REPORT_TYPE_ID,
+        &[
+            (DESCRIPTION_KEY, DESCRIPTION_ID),
+            (PARTICIPATION_KEY, PARTICIPATION_ID),
+            (POTENTIAL_DATE_KEY, POTENTIAL_DATE_ID),
+            (TIMESCOPE_KEY, TIMESCOPE_ID),
+            (TOPIC_KEY, TOPIC_ID),
+            (TYPE_KEY, TYPE_ID),
+        ],
+        &[
+            (
+                PARTICIPATION_KEY,
+                &[
+                    (PARTICIPATION_HELPING_KEY, PARTICIPATION_HELPING_ID),
+                    (PARTICIPATION_RESPONSIBLE_KEY, PARTICIPATION_RESPONSIBLE_ID),
+                ],
+            ),
+            (
+                TIMESCOPE_KEY,
+                &[
+                    (TIMESCOPE_BOTH_KEY, TIMESCOPE_BOTH_ID),
+                    (TIMESCOPE_FULL_KEY, TIMESCOPE_FULL_ID),
+                    (TIMESCOPE_HALF_KEY, TIMESCOPE_HALF_ID),
+                    (TIMESCOPE_OTHER_KEY, TIMESCOPE_OTHER_ID),
+                ],
+            ),
+            (
+                TYPE_KEY,
+                &[
+                    (TYPE_EVENT_KEY, TYPE_EVENT_ID),
+                    (TYPE_TRAINING_KEY, TYPE_TRAINING_ID),
+                ],
+            ),
+        ],
+    )
+}
+
 impl Reports for Vec<RosterReport> {
     fn new_from_reports(
         report_type: &response::ReportTypesItem,
         reports: response::Reports,
         users: &HashMap<String, response::Consumer>,
-    ) -> Result<Self>
+        mapping: &Mapping,
+        lenient: bool,
+    ) -> Result<Parsed<Self>>
     where
         Self: Sized,
     {
-        let mut roster_reports: Vec<RosterReport> = Vec::default();
-        for report in reports.items {
-            let user = users
-                .get(&report.user_cluster_relation_id.to_string())
-                .cloned()
-                .unwrap_or_default();
-            roster_reports.push(RosterReport::new_from_report(&report_type, &report, &user)?);
-        }
-        Ok(roster_reports)
+        parse_reports(reports, users, lenient, |report, user| {
+            RosterReport::new_from_report(report_type, report, user, mapping)
+        })
+    }
+}
+
+impl Export for Vec<RosterReport> {
+    fn title(&self) -> String {
+        TITLE.to_string()
+    }
+
+    fn columns(&self) -> Vec<String> {
+        columns::<RosterReport>()
     }
 
     fn print(self) {
@@ -178,10 +247,7 @@ impl Reports for Vec<RosterReport> {
         println!("{table}");
     }
 
-    fn write_xlsx(self, path: &std::path::Path) -> Result<()> {
-        let mut workbook = Workbook::new();
-        workbook.read_only_recommended();
-
+    fn add_worksheet(self, workbook: &mut Workbook) -> Result<()> {
         let worksheet = workbook.add_worksheet().set_name(TITLE)?;
         set_table(worksheet, &ROSTER_REPORTS_HEADERS, self.len())?;
 
@@ -209,16 +275,15 @@ impl Reports for Vec<RosterReport> {
             worksheet.write(row, 7, report.description)?;
         }
         worksheet.autofit();
-        workbook.save(path)?;
         Ok(())
     }
 }
 
 impl Type {
-    pub fn new(id: &str) -> Result<Self> {
-        let variant = match id {
-            TYPE_EVENT_ID => Self::Event,
-            TYPE_TRAINING_ID => Self::Training,
+    pub fn new(id: &str, mapping: &Mapping) -> Result<Self> {
+        let variant = match mapping.option(TYPE_KEY, id) {
+            Some(TYPE_EVENT_KEY) => Self::Event,
+            Some(TYPE_TRAINING_KEY) => Self::Training,
             _ => bail!("Unknow type variant \"{}\"", id),
         };
 
@@ -236,10 +301,10 @@ impl Display for Type {
 }
 
 impl Participation {
-    pub fn new(id: &str) -> Result<Self> {
-        let variant = match id {
-            PARTICIPATION_HELPING_ID => Self::Helping,
-            PARTICIPATION_RESPONSIBLE_ID => Self::Responsible,
+    pub fn new(id: &str, mapping: &Mapping) -> Result<Self> {
+        let variant = match mapping.option(PARTICIPATION_KEY, id) {
+            Some(PARTICIPATION_HELPING_KEY) => Self::Helping,
+            Some(PARTICIPATION_RESPONSIBLE_KEY) => Self::Responsible,
             _ => bail!("Unknow type variant \"{}\"", id),
         };
         Ok(variant)
@@ -256,12 +321,12 @@ impl Display for Participation {
 }
 
 impl TimeScope {
-    pub fn new(id: &str) -> Result<Self> {
-        let variant = match id {
-            TIMESCOPE_BOTH_ID => Self::Both,
-            TIMESCOPE_FULL_ID => Self::Full,
-            TIMESCOPE_HALF_ID => Self::Half,
-            TIMESCOPE_OTHER_ID => Self::Other,
+    pub fn new(id: &str, mapping: &Mapping) -> Result<Self> {
+        let variant = match mapping.option(TIMESCOPE_KEY, id) {
+            Some(TIMESCOPE_BOTH_KEY) => Self::Both,
+            Some(TIMESCOPE_FULL_KEY) => Self::Full,
+            Some(TIMESCOPE_HALF_KEY) => Self::Half,
+            Some(TIMESCOPE_OTHER_KEY) => Self::Other,
             _ => bail!("Unknow type variant \"{}\"", id),
         };
         Ok(variant)
@@ -1,11 +1,13 @@
 use std::{collections::HashMap, fmt::Display};
 
-use super::{parse_string, set_table, Reports};
-use crate::divera::schema::response;
+use super::{columns, parse_reports, parse_string, set_table, Export, Parsed, Reports};
+use crate::{config::Mapping, divera::schema::response};
 use anyhow::{bail, Context, Result};
 use comfy_table::{ContentArrangement, Table};
 use rust_xlsxwriter::Workbook;
+use serde::Serialize;
 
+const REPORT_TYPE_ID: i64 = 14307;
 const NOTE_ID: &str = "383b1c3c-4470-440a-bf03-27b315778576";
 const TYPE_CLARIFICATION_ID: &str = "97d63a1a-f497-4e2c-bfa4-666038553b7a";
 const TYPE_DESIGN_ID: &str = "e499b1dd-5977-47d1-a554-bff91f7e3ef0";
@@ -13,6 +15,13 @@ const TYPE_ID: &str = "35e2d05a-1368-43b5-8611-4afc319c95da";
 const TYPE_IMPROVEMENT_ID: &str = "afcb458f-635b-43c9-afbb-55280f8fd2f1";
 const TYPE_PROBLEM_ID: &str = "ff6b3ae9-9378-4f92-bd4f-b1203c48aff3";
 
+const NOTE_KEY: &str = "note";
+const TYPE_CLARIFICATION_KEY: &str = "clarification";
+const TYPE_DESIGN_KEY: &str = "design";
+const TYPE_IMPROVEMENT_KEY: &str = "improvement";
+const TYPE_KEY: &str = "type";
+const TYPE_PROBLEM_KEY: &str = "problem";
+
 const TITLE: &str = "Verbesserungsv_Feuerwehrhaus";
 const NOTE_TEXT: &str = "Mitteilung";
 const TYPE_CLARIFICATION_TEXT: &str = "Klärungsbedarf";
@@ -23,7 +32,7 @@ const TYPE_TEXT: &str = "Art";
 
 const STATION_REPORTS_HEADERS: [&str; 4] = ["ID", "Mitglied", TYPE_TEXT, NOTE_TEXT];
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize)]
 pub struct StationReport {
     pub id: i64,
     pub user: String,
@@ -31,7 +40,8 @@ pub struct StationReport {
     pub note: String,
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Type {
     Clarification,
     Design,
@@ -45,6 +55,7 @@ impl StationReport {
         report_type: &response::ReportTypesItem,
         report: &response::Report,
         user: &response::Consumer,
+        mapping: &Mapping,
     ) -> Result<Self> {
         let mut station_report = StationReport {
             id: report.id,
@@ -53,12 +64,12 @@ impl StationReport {
         };
 
         for (field, field_type) in report.fields.iter().zip(report_type.fields.iter()) {
-            match field_type.id.as_str() {
-                TYPE_ID => {
+            match mapping.field(&field_type.id) {
+                Some(TYPE_KEY) => {
                     let id = parse_string(field).context("Failed to get type id")?;
-                    station_report.r#type = Type::new(&id)?;
+                    station_report.r#type = Type::new(&id, mapping)?;
                 }
-                NOTE_ID => {
+                Some(NOTE_KEY) => {
                     station_report.note = parse_string(field).context("Failed to get note")?;
                 }
                 _ => bail!("Unknown station report type \"{}\"", field_type.name),
@@ -69,27 +80,47 @@ impl StationReport {
     }
 }
 
+pub fn default_mapping() -> Mapping {
+    Mapping::new(
+        REPORT_TYPE_ID,
+        &[(NOTE_KEY, NOTE_ID), (TYPE_KEY, TYPE_ID)],
+        &[(
+            TYPE_KEY,
+            &[
+                (TYPE_CLARIFICATION_KEY, TYPE_CLARIFICATION_ID),
+                (TYPE_DESIGN_KEY, TYPE_DESIGN_ID),
+                (TYPE_IMPROVEMENT_KEY, TYPE_IMPROVEMENT_ID),
+                (TYPE_PROBLEM_KEY, TYPE_PROBLEM_ID),
+            ],
+        )],
+    )
+}
+
 impl Reports for Vec<StationReport> {
     fn new_from_reports(
         report_type: &response::ReportTypesItem,
         reports: response::Reports,
         users: &HashMap<String, response::Consumer>,
-    ) -> Result<Self>
+        mapping: &Mapping,
+        lenient: bool,
+    ) -> Result<Parsed<Self>>
     where
         Self: Sized,
     {
-        let mut station_reports: Vec<StationReport> = Vec::default();
-        for report in reports.items {
-            let user = users
-                .get(&report.user_cluster_relation_id.to_string())
-                .cloned()
-                .unwrap_or_default();
-            let station_report = StationReport::new_from_report(report_type, &report, &user)
-                .context("Failed to create station report")?;
-            station_reports.push(station_report);
-        }
+        parse_reports(reports, users, lenient, |report, user| {
+            StationReport::new_from_report(report_type, report, user, mapping)
+                .context("Failed to create station report")
+        })
+    }
+}
+
+impl Export for Vec<StationReport> {
+    fn title(&self) -> String {
+        TITLE.to_string()
+    }
 
-        Ok(station_reports)
+    fn columns(&self) -> Vec<String> {
+        columns::<StationReport>()
     }
 
     fn print(self) {
@@ -108,10 +139,7 @@ impl Reports for Vec<StationReport> {
         println!("{table}");
     }
 
-    fn write_xlsx(self, path: &std::path::Path) -> Result<()> {
-        let mut workbook = Workbook::new();
-        workbook.read_only_recommended();
-
+    fn add_worksheet(self, workbook: &mut Workbook) -> Result<()> {
         let worksheet = workbook.add_worksheet().set_name(TITLE)?;
         set_table(worksheet, &STATION_REPORTS_HEADERS, self.len())?;
 
@@ -123,18 +151,17 @@ impl Reports for Vec<StationReport> {
             worksheet.write(row, 3, report.note)?;
         }
         worksheet.autofit();
-        workbook.save(path)?;
         Ok(())
     }
 }
 
 impl Type {
-    pub fn new(id: &str) -> Result<Self> {
-        let variant = match id {
-            TYPE_CLARIFICATION_ID => Self::Clarification,
-            TYPE_DESIGN_ID => Self::Design,
-            TYPE_IMPROVEMENT_ID => Self::Improvement,
-            TYPE_PROBLEM_ID => Self::Problem,
+    pub fn new(id: &str, mapping: &Mapping) -> Result<Self> {
+        let variant = match mapping.option(TYPE_KEY, id) {
+            Some(TYPE_CLARIFICATION_KEY) => Self::Clarification,
+            Some(TYPE_DESIGN_KEY) => Self::Design,
+            Some(TYPE_IMPROVEMENT_KEY) => Self::Improvement,
+            Some(TYPE_PROBLEM_KEY) => Self::Problem,
             _ => bail!("Unknow type variant \"{}\"", id),
         };
 
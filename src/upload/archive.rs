@@ -0,0 +1,57 @@
+use anyhow::Result;
+use chrono::{Days, NaiveDate};
+use std::path::Path;
+
+use super::UploadTarget;
+use crate::config::Archive;
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Uploads a copy of the file named by the date into the archive directory next to the file.
+/// Copies of the same file which are older than the retention period are removed, copies of
+/// other files sharing the archive directory are kept.
+pub fn store(
+    archive: &Archive,
+    target: &dyn UploadTarget,
+    path: &Path,
+    file_name: &str,
+    date: NaiveDate,
+) -> Result<()> {
+    let (directory, name) = match file_name.rsplit_once('/') {
+        Some((parent, name)) => (format!("{parent}/{}", archive.directory), name),
+        None => (archive.directory.clone(), file_name),
+    };
+    let (stem, extension) = split_extension(name);
+    let archived = format!("{directory}/{stem}_{}{extension}", date.format(DATE_FORMAT));
+    target.upload(path, &archived)?;
+
+    let Some(retention_days) = archive.retention_days else {
+        return Ok(());
+    };
+    let Some(expiry) = date.checked_sub_days(Days::new(retention_days)) else {
+        return Ok(());
+    };
+    for name in target.list(&directory)? {
+        if archived_date(&name, stem, extension).is_some_and(|archived| archived < expiry) {
+            log::info!("Removing expired archive \"{directory}/{name}\"");
+            target.delete(&format!("{directory}/{name}"))?;
+        }
+    }
+    Ok(())
+}
+
+fn split_extension(name: &str) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(index) if index > 0 => name.split_at(index),
+        _ => (name, ""),
+    }
+}
+
+/// Date of an archived copy of the file with the stem and extension, none for other files
+fn archived_date(name: &str, stem: &str, extension: &str) -> Option<NaiveDate> {
+    let date = name
+        .strip_prefix(stem)?
+        .strip_prefix('_')?
+        .strip_suffix(extension)?;
+    NaiveDate::parse_from_str(date, DATE_FORMAT).ok()
+}
@@ -0,0 +1,69 @@
+use anyhow::{bail, Context, Result};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::UploadTarget;
+use crate::config::Directory;
+
+/// Copies into a local or mounted network directory
+pub struct DirectoryTarget {
+    name: String,
+    path: PathBuf,
+}
+
+impl DirectoryTarget {
+    pub fn new(name: &str, config: Directory) -> Self {
+        Self {
+            name: name.to_string(),
+            path: PathBuf::from(config.path),
+        }
+    }
+}
+
+impl UploadTarget for DirectoryTarget {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn upload(&self, path: &Path, file_name: &str) -> Result<()> {
+        let destination = self.path.join(file_name);
+        let (Some(directory), Some(name)) = (destination.parent(), destination.file_name()) else {
+            bail!("Invalid file name \"{file_name}\"");
+        };
+        fs::create_dir_all(directory)
+            .with_context(|| format!("Failed to create {}", directory.display()))?;
+        // Readers of the directory must never see a partially copied file
+        let partial = directory.join(format!(".{}.part", name.to_string_lossy()));
+        fs::copy(path, &partial)
+            .with_context(|| format!("Failed to copy to {}", partial.display()))?;
+        fs::rename(&partial, &destination)
+            .with_context(|| format!("Failed to move {}", partial.display()))?;
+        Ok(())
+    }
+
+    fn list(&self, directory: &str) -> Result<Vec<String>> {
+        let path = self.path.join(directory);
+        let entries = match fs::read_dir(&path) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::default()),
+            Err(error) => {
+                return Err(error).with_context(|| format!("Failed to list {}", path.display()))
+            }
+        };
+        let mut names = Vec::default();
+        for entry in entries {
+            let entry = entry.with_context(|| format!("Failed to list {}", path.display()))?;
+            if entry.file_type()?.is_file() {
+                names.push(entry.file_name().to_string_lossy().to_string());
+            }
+        }
+        Ok(names)
+    }
+
+    fn delete(&self, file_name: &str) -> Result<()> {
+        let path = self.path.join(file_name);
+        fs::remove_file(&path).with_context(|| format!("Failed to delete {}", path.display()))
+    }
+}
@@ -0,0 +1,117 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::hex;
+
+/// Fingerprints of the last upload of every file per target, kept in a local json file.
+/// Files are only uploaded again when their content changed.
+#[derive(Clone, Debug)]
+pub struct Fingerprints {
+    path: PathBuf,
+}
+
+/// Target name to file name to last upload
+type Entries = BTreeMap<String, BTreeMap<String, Upload>>;
+
+/// Fingerprints of the last upload of a file
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(from = "StoredUpload")]
+pub struct Upload {
+    /// Fingerprint of the whole content
+    pub fingerprint: String,
+    /// Fingerprints of the single entries, to find the new ones
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub entries: BTreeSet<String>,
+}
+
+/// Former files only kept the fingerprint of the content, without the entries
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredUpload {
+    Fingerprint(String),
+    Upload {
+        fingerprint: String,
+        #[serde(default)]
+        entries: BTreeSet<String>,
+    },
+}
+
+impl From<StoredUpload> for Upload {
+    fn from(stored: StoredUpload) -> Self {
+        match stored {
+            StoredUpload::Fingerprint(fingerprint) => Self {
+                fingerprint,
+                entries: BTreeSet::default(),
+            },
+            StoredUpload::Upload {
+                fingerprint,
+                entries,
+            } => Self {
+                fingerprint,
+                entries,
+            },
+        }
+    }
+}
+
+impl Fingerprints {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the fingerprints of the last upload of the file to the target
+    pub fn load(&self, target: &str, file_name: &str) -> Option<Upload> {
+        let mut entries = self.entries().ok()?;
+        entries.get_mut(target)?.remove(file_name)
+    }
+
+    pub fn store(&self, target: &str, file_name: &str, upload: Upload) -> Result<()> {
+        // An unreadable file is replaced, which only costs one more upload of every file
+        let mut entries = self.entries().unwrap_or_default();
+        entries
+            .entry(target.to_string())
+            .or_default()
+            .insert(file_name.to_string(), upload);
+        let content = serde_json::to_string_pretty(&entries)?;
+        fs::write(&self.path, content)
+            .with_context(|| format!("Failed to write fingerprints {}", self.path.display()))
+    }
+
+    fn entries(&self) -> Result<Entries> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Entries::default()),
+            Err(error) => {
+                log::warn!(
+                    "Failed to read fingerprints {}: {error}",
+                    self.path.display()
+                );
+                return Err(error.into());
+            }
+        };
+        serde_json::from_str(&content).map_err(|error| {
+            log::warn!(
+                "Ignoring invalid fingerprints {}: {error}",
+                self.path.display()
+            );
+            error.into()
+        })
+    }
+}
+
+/// Hash of the serialized value. Values are serialized in a fixed order,
+/// so the fingerprint only changes with the content.
+pub fn fingerprint(value: &(impl Serialize + ?Sized)) -> Result<String> {
+    let json = serde_json::to_vec(value).context("Failed to serialize fingerprint")?;
+    Ok(hex(&Sha256::digest(json)))
+}
@@ -0,0 +1,119 @@
+//! Targets the exported reports are uploaded to, configured in [`crate::config::Target`]
+
+pub mod archive;
+pub mod directory;
+pub mod fingerprints;
+pub mod s3;
+pub mod sftp;
+pub mod smtp;
+pub mod webdav;
+
+use anyhow::{Context, Result};
+use chrono::Local;
+use std::path::Path;
+
+use crate::config::Archive;
+use fingerprints::{fingerprint, Fingerprints, Upload};
+
+pub trait UploadTarget {
+    /// Name of the target in the config
+    fn name(&self) -> &str;
+    /// Uploads the local file under the file name, which may contain subdirectories
+    fn upload(&self, path: &Path, file_name: &str) -> Result<()>;
+    /// Uploads the file together with a summary of the entries which are new since the last upload.
+    /// Only targets which notify people make use of the summary.
+    fn deliver(&self, path: &Path, file_name: &str, _new: &Summary) -> Result<()> {
+        self.upload(path, file_name)
+    }
+    /// Whether dated copies are archived on the target
+    fn archives(&self) -> bool {
+        true
+    }
+    /// Names of the files in the directory, empty if the directory does not exist
+    fn list(&self, directory: &str) -> Result<Vec<String>>;
+    fn delete(&self, file_name: &str) -> Result<()>;
+}
+
+/// Entries of an uploaded file with one line per report
+#[derive(Clone, Debug, Default)]
+pub struct Summary {
+    pub title: String,
+    pub entries: Vec<String>,
+}
+
+/// Targets of a report together with the fingerprints of the previous uploads
+pub struct Destinations {
+    pub targets: Vec<Box<dyn UploadTarget>>,
+    /// Without fingerprints every file is uploaded
+    pub fingerprints: Option<Fingerprints>,
+    /// Dated copies which are uploaded next to the files
+    pub archive: Option<Archive>,
+}
+
+impl Destinations {
+    /// Uploads the local file to every target which did not receive the same content before
+    pub fn upload(
+        &self,
+        path: &Path,
+        file_name: &str,
+        fingerprint: &str,
+        summary: &Summary,
+    ) -> Result<()> {
+        let today = Local::now().date_naive();
+        let entries = summary
+            .entries
+            .iter()
+            .map(|entry| entry_fingerprint(entry))
+            .collect::<Result<Vec<String>>>()?;
+        for target in &self.targets {
+            let previous = self
+                .fingerprints
+                .as_ref()
+                .and_then(|fingerprints| fingerprints.load(target.name(), file_name))
+                .unwrap_or_default();
+            if previous.fingerprint == fingerprint {
+                log::info!("Skipping unchanged \"{file_name}\" for {}", target.name());
+                continue;
+            }
+
+            let new = Summary {
+                title: summary.title.clone(),
+                entries: summary
+                    .entries
+                    .iter()
+                    .zip(&entries)
+                    .filter(|(_, entry)| !previous.entries.contains(*entry))
+                    .map(|(line, _)| line.clone())
+                    .collect(),
+            };
+            log::info!("Uploading \"{file_name}\" to {}", target.name());
+            target.deliver(path, file_name, &new).with_context(|| {
+                format!("Failed to upload \"{file_name}\" to \"{}\"", target.name())
+            })?;
+            if let Some(archive) = self.archive.as_ref().filter(|_| target.archives()) {
+                archive::store(archive, target.as_ref(), path, file_name, today).with_context(
+                    || format!("Failed to archive \"{file_name}\" in \"{}\"", target.name()),
+                )?;
+            }
+            if let Some(fingerprints) = &self.fingerprints {
+                let upload = Upload {
+                    fingerprint: fingerprint.to_string(),
+                    entries: entries.iter().cloned().collect(),
+                };
+                fingerprints.store(target.name(), file_name, upload)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Short fingerprint of an entry, which only has to distinguish the entries of a file
+fn entry_fingerprint(entry: &str) -> Result<String> {
+    let mut fingerprint = fingerprint(entry)?;
+    fingerprint.truncate(16);
+    Ok(fingerprint)
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
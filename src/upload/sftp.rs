@@ -0,0 +1,132 @@
+use anyhow::{bail, Context, Result};
+use std::{
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use super::UploadTarget;
+use crate::config::Sftp;
+
+/// Prompt which sftp prints before every command of a batch
+const PROMPT: &str = "sftp>";
+
+/// Uploads into a directory of a ssh server by running the sftp program in batch mode
+pub struct SftpTarget {
+    name: String,
+    config: Sftp,
+}
+
+impl SftpTarget {
+    pub fn new(name: &str, config: Sftp) -> Self {
+        Self {
+            name: name.to_string(),
+            config,
+        }
+    }
+
+    /// Path of the file on the server
+    fn remote(&self, file_name: &str) -> String {
+        let directory = &self.config.directory;
+        if directory.is_empty() || directory.ends_with('/') {
+            format!("{directory}{file_name}")
+        } else {
+            format!("{directory}/{file_name}")
+        }
+    }
+
+    /// Commands which create the missing directories and put the file
+    fn batch(&self, path: &Path, file_name: &str) -> String {
+        let remote = self.remote(file_name);
+        let mut batch = String::new();
+        // Parents have to be created before their children, the leading dash
+        // ignores the error of an existing directory
+        for (end, _) in remote.match_indices('/') {
+            let parent = &remote[..end];
+            if !parent.is_empty() && !parent.ends_with('/') {
+                batch.push_str(&format!("-mkdir {}\n", quote(parent)));
+            }
+        }
+
+        batch.push_str(&format!(
+            "put {} {}\n",
+            quote(&path.to_string_lossy()),
+            quote(&remote)
+        ));
+        batch
+    }
+
+    /// Runs the commands and returns the output
+    fn run(&self, batch: &str) -> Result<String> {
+        let mut command = Command::new(&self.config.program);
+        // Batch mode reads the commands from stdin and aborts on the first failure
+        command.args(["-b", "-"]);
+        if let Some(port) = self.config.port {
+            command.arg("-P").arg(port.to_string());
+        }
+        if let Some(identity_file) = &self.config.identity_file {
+            command.arg("-i").arg(identity_file);
+        }
+        let destination = match &self.config.username {
+            Some(username) => format!("{username}@{}", self.config.host),
+            None => self.config.host.clone(),
+        };
+        let mut child = command
+            .arg(destination)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .with_context(|| format!("Failed to run \"{}\"", self.config.program))?;
+
+        let mut stdin = child.stdin.take().context("Failed to open sftp input")?;
+        stdin
+            .write_all(batch.as_bytes())
+            .context("Failed to write sftp commands")?;
+        drop(stdin);
+
+        let output = child
+            .wait_with_output()
+            .context("Failed to wait for sftp")?;
+        if !output.status.success() {
+            bail!("sftp failed with {}", output.status);
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+}
+
+impl UploadTarget for SftpTarget {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn upload(&self, path: &Path, file_name: &str) -> Result<()> {
+        self.run(&self.batch(path, file_name))?;
+        Ok(())
+    }
+
+    fn list(&self, directory: &str) -> Result<Vec<String>> {
+        // A missing directory is ignored and lists nothing
+        let output = self.run(&format!("-ls -1 {}\n", quote(&self.remote(directory))))?;
+        let names = output
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with(PROMPT))
+            .filter_map(|line| line.trim_end().rsplit('/').next())
+            .map(String::from)
+            .collect();
+        Ok(names)
+    }
+
+    fn delete(&self, file_name: &str) -> Result<()> {
+        self.run(&format!("rm {}\n", quote(&self.remote(file_name))))?;
+        Ok(())
+    }
+}
+
+/// Quotes an argument of a sftp batch command
+fn quote(argument: &str) -> String {
+    format!(
+        "\"{}\"",
+        argument.replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
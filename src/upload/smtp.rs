@@ -0,0 +1,155 @@
+use anyhow::{bail, Context, Result};
+use chrono::Local;
+use lettre::{
+    message::{header::ContentType, Attachment, Mailbox, MultiPart, SinglePart},
+    transport::smtp::authentication::Credentials,
+    Message, SmtpTransport, Transport,
+};
+use std::{fs, path::Path, time::Duration};
+
+use super::{Summary, UploadTarget};
+use crate::{
+    config::{Smtp, SmtpSecurity},
+    reports::file_name,
+};
+
+/// Entries which are listed in the body, further ones are only counted
+const MAX_LISTED_ENTRIES: usize = 50;
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Sends the report as attachment of a mail to the recipients.
+/// Mails can neither be listed nor deleted, so no archive is kept.
+pub struct SmtpTarget {
+    name: String,
+    config: Smtp,
+}
+
+impl SmtpTarget {
+    pub fn new(name: &str, config: Smtp) -> Self {
+        Self {
+            name: name.to_string(),
+            config,
+        }
+    }
+
+    fn transport(&self) -> Result<SmtpTransport> {
+        let host = &self.config.host;
+        let builder = match self.config.security {
+            SmtpSecurity::Starttls => SmtpTransport::starttls_relay(host),
+            SmtpSecurity::Tls => SmtpTransport::relay(host),
+            SmtpSecurity::None => Ok(SmtpTransport::builder_dangerous(host)),
+        }
+        .with_context(|| format!("Invalid mail server \"{host}\""))?;
+        let mut builder = builder.timeout(Some(TIMEOUT));
+        if let Some(port) = self.config.port {
+            builder = builder.port(port);
+        }
+        if let Some(username) = &self.config.username {
+            let password = self
+                .config
+                .password
+                .resolve()
+                .context("Failed to read smtp password")?;
+            builder = builder.credentials(Credentials::new(username.clone(), password));
+        }
+        Ok(builder.build())
+    }
+
+    fn message(&self, path: &Path, file_name: &str, new: &Summary) -> Result<Message> {
+        let subject =
+            file_name::render(&self.config.subject, &new.title, Local::now().date_naive())?;
+        let mut builder = Message::builder()
+            .from(mailbox(&self.config.from)?)
+            .subject(subject);
+        if self.config.recipients.is_empty() {
+            bail!("No recipients configured");
+        }
+        for recipient in &self.config.recipients {
+            builder = builder.to(mailbox(recipient)?);
+        }
+
+        let content =
+            fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        // The file name may contain subdirectories, which are meaningless for an attachment
+        let attachment_name = file_name.rsplit('/').next().unwrap_or(file_name);
+        let content_type =
+            ContentType::parse(content_type(attachment_name)).expect("Content types are valid");
+        let attachment = Attachment::new(attachment_name.to_string()).body(content, content_type);
+
+        builder
+            .multipart(
+                MultiPart::mixed()
+                    .singlepart(SinglePart::plain(body(new)))
+                    .singlepart(attachment),
+            )
+            .context("Failed to build mail")
+    }
+}
+
+impl UploadTarget for SmtpTarget {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn upload(&self, path: &Path, file_name: &str) -> Result<()> {
+        self.deliver(path, file_name, &Summary::default())
+    }
+
+    fn deliver(&self, path: &Path, file_name: &str, new: &Summary) -> Result<()> {
+        let message = self.message(path, file_name, new)?;
+        self.transport()?
+            .send(&message)
+            .with_context(|| format!("Failed to send mail via {}", self.config.host))?;
+        Ok(())
+    }
+
+    fn archives(&self) -> bool {
+        false
+    }
+
+    fn list(&self, _directory: &str) -> Result<Vec<String>> {
+        Ok(Vec::default())
+    }
+
+    fn delete(&self, file_name: &str) -> Result<()> {
+        bail!("Sent mail with \"{file_name}\" can not be deleted")
+    }
+}
+
+fn mailbox(address: &str) -> Result<Mailbox> {
+    address
+        .parse()
+        .with_context(|| format!("Invalid mail address \"{address}\""))
+}
+
+/// Text of the mail listing the new entries of the report
+fn body(new: &Summary) -> String {
+    let mut body = match new.entries.len() {
+        0 => format!("{}: keine neuen Einträge\n", new.title),
+        1 => format!("{}: 1 neuer Eintrag\n\n", new.title),
+        count => format!("{}: {count} neue Einträge\n\n", new.title),
+    };
+    for entry in new.entries.iter().take(MAX_LISTED_ENTRIES) {
+        body.push_str(&format!("- {entry}\n"));
+    }
+    if new.entries.len() > MAX_LISTED_ENTRIES {
+        body.push_str(&format!(
+            "… und {} weitere\n",
+            new.entries.len() - MAX_LISTED_ENTRIES
+        ));
+    }
+    body.push_str("\nDer vollständige Bericht ist angehängt.\n");
+    body
+}
+
+fn content_type(file_name: &str) -> &'static str {
+    match file_name.rsplit_once('.').map(|(_, extension)| extension) {
+        Some("xlsx") => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        Some("csv") => "text/csv",
+        Some("json") => "application/json",
+        Some("ndjson") => "application/x-ndjson",
+        Some("ics") => "text/calendar",
+        Some("pdf") => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
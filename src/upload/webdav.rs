@@ -0,0 +1,346 @@
+use anyhow::{anyhow, bail, Context, Result};
+use percent_encoding::percent_decode_str;
+use reqwest::{
+    blocking::{Client, RequestBuilder, Response},
+    header::{CONTENT_TYPE, ETAG},
+    Method, StatusCode,
+};
+use std::{
+    fs::{self, File},
+    path::Path,
+    time::Duration,
+};
+use url::Url;
+
+use super::UploadTarget;
+use crate::config::WebDav;
+
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:">
+  <d:prop><d:getcontentlength/><d:getetag/><d:resourcetype/></d:prop>
+</d:propfind>"#;
+
+/// Uploads into the root directory of a webdav server.
+/// Missing directories are created and every upload is verified afterwards.
+pub struct WebDavTarget {
+    name: String,
+    config: WebDav,
+    http: Client,
+}
+
+/// Authenticated connection to the webdav server
+struct Connection<'a> {
+    http: &'a Client,
+    username: String,
+    password: String,
+}
+
+/// Properties of a resource returned by PROPFIND
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Properties {
+    size: Option<u64>,
+    etag: Option<String>,
+}
+
+impl WebDavTarget {
+    /// A server which does not respond within the timeouts fails the upload instead of blocking it
+    pub fn new(
+        name: &str,
+        config: WebDav,
+        connect_timeout: Duration,
+        timeout: Duration,
+    ) -> Result<Self> {
+        let http = Client::builder()
+            .connect_timeout(connect_timeout)
+            .timeout(timeout)
+            .build()
+            .context("Failed to create webdav client")?;
+        Ok(Self {
+            name: name.to_string(),
+            config,
+            http,
+        })
+    }
+
+    /// Checks that the root directory is reachable with the credentials
+    pub fn check(&self) -> Result<()> {
+        let root = self.root()?;
+        let response = self.connect()?.propfind(&root, "0")?;
+        if !response.status().is_success() {
+            return Err(status_error("PROPFIND", &root, response));
+        }
+        Ok(())
+    }
+
+    fn connect(&self) -> Result<Connection<'_>> {
+        let password = self
+            .config
+            .password
+            .resolve()
+            .context("Failed to read webdav password")?;
+        Ok(Connection {
+            http: &self.http,
+            username: self.config.username.clone(),
+            password,
+        })
+    }
+
+    fn root(&self) -> Result<Url> {
+        let root = &self.config.root_directory;
+        Url::parse(root).with_context(|| format!("Invalid webdav root directory \"{root}\""))
+    }
+
+    /// Url of the file below the root directory, a file name may contain subdirectories
+    fn url(&self, file_name: &str) -> Result<Url> {
+        let mut url = self.root()?;
+        url.path_segments_mut()
+            .map_err(|_| anyhow!("Webdav root directory has to be an http url"))?
+            .pop_if_empty()
+            .extend(file_name.split('/'));
+        Ok(url)
+    }
+}
+
+impl UploadTarget for WebDavTarget {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn upload(&self, path: &Path, file_name: &str) -> Result<()> {
+        let connection = self.connect()?;
+        let url = self.url(file_name)?;
+        let size = fs::metadata(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?
+            .len();
+
+        let mut response = connection.put(path, &url)?;
+        // The parent collection is missing, which most servers answer with 409 and some with 404
+        if matches!(
+            response.status(),
+            StatusCode::CONFLICT | StatusCode::NOT_FOUND
+        ) {
+            if let Some(parent) = parent(&url) {
+                create_collection(&connection, &parent)?;
+                response = connection.put(path, &url)?;
+            }
+        }
+        if !response.status().is_success() {
+            return Err(status_error("PUT", &url, response));
+        }
+
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .map(String::from);
+        verify(&connection, &url, size, etag.as_deref())
+    }
+
+    fn list(&self, directory: &str) -> Result<Vec<String>> {
+        let mut url = self.url(directory)?;
+        // Collections are addressed with a trailing slash
+        url.path_segments_mut()
+            .map_err(|_| anyhow!("Webdav root directory has to be an http url"))?
+            .pop_if_empty()
+            .push("");
+        let response = self.connect()?.propfind(&url, "1")?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(Vec::default());
+        }
+        if !response.status().is_success() {
+            return Err(status_error("PROPFIND", &url, response));
+        }
+        let body = response
+            .text()
+            .with_context(|| format!("Failed to read PROPFIND response of {url}"))?;
+        file_names(&body).with_context(|| format!("Invalid PROPFIND response of {url}"))
+    }
+
+    fn delete(&self, file_name: &str) -> Result<()> {
+        let connection = self.connect()?;
+        let url = self.url(file_name)?;
+        let response = connection.send(connection.request(b"DELETE", &url), &url)?;
+        // A file which is gone already needs no deletion
+        if !response.status().is_success() && response.status() != StatusCode::NOT_FOUND {
+            return Err(status_error("DELETE", &url, response));
+        }
+        Ok(())
+    }
+}
+
+impl Connection<'_> {
+    fn request(&self, method: &[u8], url: &Url) -> RequestBuilder {
+        let method = Method::from_bytes(method).expect("WebDAV methods are valid tokens");
+        self.http
+            .request(method, url.clone())
+            .basic_auth(&self.username, Some(&self.password))
+    }
+
+    fn send(&self, request: RequestBuilder, url: &Url) -> Result<Response> {
+        request
+            .send()
+            .with_context(|| format!("Failed to connect to webdav server for {url}"))
+    }
+
+    fn put(&self, path: &Path, url: &Url) -> Result<Response> {
+        let file =
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let request = self
+            .request(b"PUT", url)
+            .header(CONTENT_TYPE, "application/octet-stream")
+            .body(file);
+        self.send(request, url)
+    }
+
+    fn mkcol(&self, url: &Url) -> Result<Response> {
+        self.send(self.request(b"MKCOL", url), url)
+    }
+
+    /// Requests the size, etag and type of the resource and with depth 1 of its members
+    fn propfind(&self, url: &Url, depth: &str) -> Result<Response> {
+        let request = self
+            .request(b"PROPFIND", url)
+            .header("Depth", depth)
+            .header(CONTENT_TYPE, "application/xml")
+            .body(PROPFIND_BODY);
+        self.send(request, url)
+    }
+}
+
+/// Creates the collection and its missing parents
+fn create_collection(connection: &Connection, url: &Url) -> Result<()> {
+    let response = connection.mkcol(url)?;
+    match response.status() {
+        status if status.is_success() => Ok(()),
+        // The collection exists already
+        StatusCode::METHOD_NOT_ALLOWED => Ok(()),
+        StatusCode::CONFLICT => {
+            let Some(parent) = parent(url) else {
+                return Err(status_error("MKCOL", url, response));
+            };
+            create_collection(connection, &parent)?;
+            let response = connection.mkcol(url)?;
+            if !response.status().is_success() {
+                return Err(status_error("MKCOL", url, response));
+            }
+            log::info!("Created webdav collection {url}");
+            Ok(())
+        }
+        _ => Err(status_error("MKCOL", url, response)),
+    }
+}
+
+/// Compares size and etag of the file on the server with the upload
+fn verify(connection: &Connection, url: &Url, size: u64, etag: Option<&str>) -> Result<()> {
+    let response = connection.propfind(url, "0")?;
+    if !response.status().is_success() {
+        return Err(status_error("PROPFIND", url, response));
+    }
+    let body = response
+        .text()
+        .with_context(|| format!("Failed to read PROPFIND response of {url}"))?;
+    let properties =
+        Properties::parse(&body).with_context(|| format!("Invalid PROPFIND response of {url}"))?;
+
+    match properties.size {
+        Some(uploaded) if uploaded == size => {}
+        Some(uploaded) => bail!("Uploaded file {url} has {uploaded} bytes instead of {size}"),
+        None => bail!("WebDAV server returned no size of {url}"),
+    }
+    if let (Some(expected), Some(actual)) = (etag, properties.etag.as_deref()) {
+        // Servers differ in quoting the etag of the PROPFIND response
+        let unquoted = |etag: &str| etag.trim_start_matches("W/").trim_matches('"').to_string();
+        if unquoted(expected) != unquoted(actual) {
+            bail!("Uploaded file {url} was changed during the upload, etag {actual} instead of {expected}");
+        }
+    }
+    log::debug!("Verified upload of {url}: {properties:?}");
+    Ok(())
+}
+
+fn parent(url: &Url) -> Option<Url> {
+    if url.path() == "/" {
+        return None;
+    }
+    let mut parent = url.clone();
+    parent.path_segments_mut().ok()?.pop_if_empty().pop();
+    Some(parent)
+}
+
+/// Error of a request which was answered with a failure status
+fn status_error(method: &str, url: &Url, response: Response) -> anyhow::Error {
+    let status = response.status();
+    let body = response.text().unwrap_or_default();
+    match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+            anyhow!("WebDAV server rejected the credentials for {method} {url} with {status}")
+        }
+        StatusCode::INSUFFICIENT_STORAGE => {
+            anyhow!("WebDAV server has no storage left for {method} {url}")
+        }
+        _ => match error_message(&body) {
+            Some(message) => {
+                anyhow!("WebDAV server answered {method} {url} with {status}: {message}")
+            }
+            None => anyhow!("WebDAV server answered {method} {url} with {status}"),
+        },
+    }
+}
+
+/// Message of an error response like the ones of Nextcloud or Apache
+fn error_message(body: &str) -> Option<String> {
+    let document = roxmltree::Document::parse(body).ok()?;
+    let message = document
+        .descendants()
+        .find(|node| node.tag_name().name() == "message")
+        .and_then(|node| node.text())
+        .unwrap_or_else(|| document.root_element().text().unwrap_or_default());
+    let message = message.trim();
+    (!message.is_empty()).then(|| message.to_string())
+}
+
+/// Names of the files, but not the collections, of a PROPFIND multistatus
+fn file_names(body: &str) -> Result<Vec<String>> {
+    let document = roxmltree::Document::parse(body)?;
+    let mut names = Vec::default();
+    for response in document
+        .descendants()
+        .filter(|node| node.has_tag_name(("DAV:", "response")))
+    {
+        let is_collection = response
+            .descendants()
+            .any(|node| node.has_tag_name(("DAV:", "collection")));
+        let href = response
+            .descendants()
+            .find(|node| node.has_tag_name(("DAV:", "href")))
+            .and_then(|node| node.text());
+        let (false, Some(href)) = (is_collection, href) else {
+            continue;
+        };
+        let name = href.trim().rsplit('/').next().unwrap_or_default();
+        names.push(percent_decode_str(name).decode_utf8()?.to_string());
+    }
+    Ok(names)
+}
+
+impl Properties {
+    /// Parses the first response of a PROPFIND multistatus
+    fn parse(body: &str) -> Result<Self> {
+        let document = roxmltree::Document::parse(body)?;
+        let property = |name: &str| {
+            document
+                .descendants()
+                .find(|node| node.has_tag_name(("DAV:", name)))
+                .and_then(|node| node.text())
+                .map(str::trim)
+        };
+
+        Ok(Self {
+            size: property("getcontentlength")
+                .map(str::parse)
+                .transpose()
+                .context("Invalid content length")?,
+            etag: property("getetag").map(String::from),
+        })
+    }
+}
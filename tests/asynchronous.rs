@@ -0,0 +1,118 @@
+#![cfg(feature = "async")]
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::{fixture, write_config_with, Response, StubServer};
+use divera_reports::{
+    config::Config,
+    divera::{AsyncDiveraClient, Settings, TokenCache},
+    reports::all::{fetch_enabled, write_combined},
+};
+use tempfile::tempdir;
+
+fn delayed_reports(name: &str) -> Response {
+    Response::new(200, &fixture(name)).with_delay(Duration::from_secs(1))
+}
+
+#[tokio::test]
+async fn reports_are_fetched_concurrently() {
+    let server = StubServer::start(vec![
+        ("/api/v2/auth/login", Response::fixture("login.json")),
+        (
+            "/api/v2/reporttypes/reports?id=10538",
+            delayed_reports("reports_absences.json"),
+        ),
+        (
+            "/api/v2/reporttypes/reports?id=11146",
+            delayed_reports("reports_fire_operation.json"),
+        ),
+    ]);
+    let mut client = AsyncDiveraClient::new(&server.url, Settings::default()).unwrap();
+    client.login("user", "secret").await.unwrap();
+
+    let start = Instant::now();
+    let reports = client.reports_of(&[10538, 11146]).await.unwrap();
+
+    assert!(start.elapsed() < Duration::from_secs(2));
+    assert_eq!(reports.len(), 2);
+    assert_eq!(reports[0].items[0].id, 101);
+    assert_eq!(reports[1].items[0].id, 201);
+}
+
+#[tokio::test]
+async fn access_token_is_cached_between_clients() {
+    let server = StubServer::start(vec![
+        ("/api/v2/auth/login", Response::fixture("login.json")),
+        ("/api/v2/auth/jwt", Response::fixture("jwt.json")),
+    ]);
+    let dir = tempdir().unwrap();
+    let cache = TokenCache::new(dir.path().join("token.json"));
+
+    for _ in 0..2 {
+        let mut client = AsyncDiveraClient::new(&server.url, Settings::default()).unwrap();
+        client.login_cached("user", "secret", &cache).await.unwrap();
+    }
+
+    assert_eq!(server.requests_to("/api/v2/auth/login").len(), 1);
+    let jwt = server.requests_to("/api/v2/auth/jwt");
+    assert_eq!(jwt.len(), 1);
+    assert!(jwt[0].query.contains("accesskey=stub-access-token"));
+}
+
+#[tokio::test]
+async fn transient_errors_are_retried() {
+    let server = StubServer::start(vec![
+        ("/api/v2/pull/all", Response::new(503, "{}")),
+        ("/api/v2/pull/all", Response::fixture("pull_all.json")),
+    ]);
+    let settings = Settings {
+        backoff: Duration::from_millis(10),
+        ..Settings::default()
+    };
+    let client = AsyncDiveraClient::new(&server.url, settings)
+        .unwrap()
+        .with_access_token("stub-access-token");
+
+    let all = client.pull_all().await.unwrap();
+
+    assert_eq!(all.cluster.consumer.len(), 2);
+    assert_eq!(server.requests_to("/api/v2/pull/all").len(), 2);
+}
+
+#[tokio::test]
+async fn enabled_reports_are_exported_concurrently() {
+    let server = StubServer::start(vec![
+        ("/api/v2/auth/login", Response::fixture("login.json")),
+        ("/api/v2/pull/all", Response::fixture("pull_all.json")),
+        (
+            "/api/v2/reporttypes/reports?id=10538",
+            delayed_reports("reports_absences.json"),
+        ),
+        (
+            "/api/v2/reporttypes/reports?id=11146",
+            delayed_reports("reports_fire_operation.json"),
+        ),
+    ]);
+    let dir = tempdir().unwrap();
+    let config = write_config_with(
+        &dir,
+        &server.url,
+        "[reports]\nenabled = [\"absences\"]\ngeneric = [11146]",
+    );
+    let config = Config::read(&config).unwrap();
+    let mut client = AsyncDiveraClient::new(&server.url, Settings::default()).unwrap();
+    client.login("user", "secret").await.unwrap();
+
+    let start = Instant::now();
+    let reports = fetch_enabled(&client, &config, false).await.unwrap();
+
+    assert!(start.elapsed() < Duration::from_secs(2));
+    let keys: Vec<String> = reports.iter().map(|reports| reports.key()).collect();
+    assert_eq!(keys, vec!["absences", "11146"]);
+    assert!(reports.iter().all(|reports| reports.errors() == 0));
+    let path = dir.path().join("Berichte.xlsx");
+    write_combined(reports, &path).unwrap();
+    assert!(path.exists());
+}
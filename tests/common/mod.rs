@@ -0,0 +1,438 @@
+#![allow(dead_code)]
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use tempfile::TempDir;
+
+/// Canned response of the stub server
+#[derive(Clone, Debug)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+    pub headers: Vec<(String, String)>,
+    /// Delay before the response is sent
+    pub delay: Duration,
+    /// Whether the connection is closed before the whole body was sent
+    pub truncated: bool,
+}
+
+/// Request which was received by the stub server
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Minimal HTTP server which answers every request to a path with a canned response.
+/// A route with a query (e.g. `/path?id=1`) only matches requests containing that query.
+/// A route given multiple times answers with its responses in order and repeats the last one.
+pub struct StubServer {
+    pub url: String,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+/// Routes of the stub server and the number of requests per route
+struct Routes {
+    routes: Vec<(String, Response)>,
+    counts: Mutex<HashMap<String, usize>>,
+}
+
+impl Response {
+    pub fn fixture(name: &str) -> Self {
+        Self::new(200, &fixture(name))
+    }
+
+    pub fn new(status: u16, body: &str) -> Self {
+        Self {
+            status,
+            body: body.to_string(),
+            headers: Vec::default(),
+            delay: Duration::ZERO,
+            truncated: false,
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Announces a longer body than is sent, so reading the body fails
+    pub fn truncated(mut self) -> Self {
+        self.truncated = true;
+        self
+    }
+}
+
+impl StubServer {
+    pub fn start(routes: Vec<(&str, Response)>) -> Self {
+        let mut routes: Vec<(String, Response)> = routes
+            .into_iter()
+            .map(|(route, response)| (route.to_string(), response))
+            .collect();
+        // The most specific route has to match first, the order of equal routes is kept
+        routes.sort_by_key(|(route, _)| std::cmp::Reverse(route.len()));
+        let routes = Arc::new(Routes {
+            routes,
+            counts: Mutex::default(),
+        });
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind stub server");
+        let url = format!("http://{}/api/", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let received = requests.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let routes = routes.clone();
+                let received = received.clone();
+                thread::spawn(move || {
+                    let request = handle(stream, &routes);
+                    received.lock().unwrap().push(request);
+                });
+            }
+        });
+
+        Self { url, requests }
+    }
+
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Requests which were received for the path
+    pub fn requests_to(&self, path: &str) -> Vec<Request> {
+        self.requests()
+            .into_iter()
+            .filter(|request| request.path == path)
+            .collect()
+    }
+}
+
+impl Routes {
+    fn response(&self, path: &str, query: &str) -> Response {
+        let matching = |(route, _): &&(String, Response)| {
+            let (route_path, route_query) = route.split_once('?').unwrap_or((route, ""));
+            route_path == path && query.contains(route_query)
+        };
+        let Some((route, _)) = self.routes.iter().find(matching) else {
+            return Response::new(404, "{}");
+        };
+        let responses: Vec<&Response> = self
+            .routes
+            .iter()
+            .filter(|(other, _)| other == route)
+            .map(|(_, response)| response)
+            .collect();
+
+        let mut counts = self.counts.lock().unwrap();
+        let count = counts.entry(route.clone()).or_default();
+        let response = responses[(*count).min(responses.len() - 1)].clone();
+        *count += 1;
+        response
+    }
+}
+
+fn handle(mut stream: TcpStream, routes: &Routes) -> Request {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).unwrap();
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or_default().to_string();
+    let (path, query) = target.split_once('?').unwrap_or((&target, ""));
+
+    let mut content_length = 0;
+    let mut headers = Vec::default();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        if line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap();
+            }
+            headers.push((name.to_string(), value.trim().to_string()));
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).unwrap();
+
+    let response = routes.response(path, query);
+    thread::sleep(response.delay);
+    let response_headers: String = response
+        .headers
+        .iter()
+        .map(|(name, value)| format!("{name}: {value}\r\n"))
+        .collect();
+    // The client might have given up on a delayed response
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {} Stub\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+        response.status,
+        response.body.len() + usize::from(response.truncated),
+        response_headers,
+        response.body
+    );
+
+    Request {
+        method,
+        path: path.to_string(),
+        query: query.to_string(),
+        headers,
+        body: String::from_utf8_lossy(&body).to_string(),
+    }
+}
+
+/// Mail received by the SMTP sink
+#[derive(Clone, Debug, Default)]
+pub struct Mail {
+    pub from: String,
+    pub recipients: Vec<String>,
+    /// Headers and body as sent by the client
+    pub data: String,
+}
+
+/// Minimal SMTP server without authentication and encryption which accepts every mail
+pub struct SmtpSink {
+    pub port: u16,
+    mails: Arc<Mutex<Vec<Mail>>>,
+}
+
+impl SmtpSink {
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind smtp sink");
+        let port = listener.local_addr().unwrap().port();
+        let mails = Arc::new(Mutex::new(Vec::new()));
+
+        let received = mails.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let received = received.clone();
+                thread::spawn(move || receive(stream, &received));
+            }
+        });
+
+        Self { port, mails }
+    }
+
+    pub fn mails(&self) -> Vec<Mail> {
+        self.mails.lock().unwrap().clone()
+    }
+}
+
+fn receive(mut stream: TcpStream, mails: &Mutex<Vec<Mail>>) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut mail = Mail::default();
+    write!(stream, "220 localhost SMTP sink\r\n").unwrap();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).unwrap_or_default() == 0 {
+            return;
+        }
+        let command = line.trim_end();
+        let verb = command
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_uppercase();
+        let address = || {
+            let start = command.find('<').map_or(0, |start| start + 1);
+            let end = command.rfind('>').unwrap_or(command.len());
+            command[start..end].to_string()
+        };
+        let reply = match verb.as_str() {
+            "EHLO" | "HELO" => "250 localhost",
+            "MAIL" => {
+                mail.from = address();
+                "250 OK"
+            }
+            "RCPT" => {
+                mail.recipients.push(address());
+                "250 OK"
+            }
+            "DATA" => {
+                write!(stream, "354 End data with <CR><LF>.<CR><LF>\r\n").unwrap();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == ".\r\n" {
+                        break;
+                    }
+                    // Leading dots are doubled by the client
+                    let line = line.strip_prefix('.').unwrap_or(&line);
+                    mail.data.push_str(line);
+                }
+                mails.lock().unwrap().push(std::mem::take(&mut mail));
+                "250 OK"
+            }
+            "QUIT" => {
+                write!(stream, "221 Bye\r\n").unwrap();
+                return;
+            }
+            _ => "250 OK",
+        };
+        write!(stream, "{reply}\r\n").unwrap();
+    }
+}
+
+/// Stub server serving the absence reports of the fixtures
+pub fn absences_server() -> StubServer {
+    StubServer::start(vec![
+        ("/api/v2/auth/login", Response::fixture("login.json")),
+        ("/api/v2/pull/all", Response::fixture("pull_all.json")),
+        (
+            "/api/v2/reporttypes/reports",
+            Response::fixture("reports_absences.json"),
+        ),
+    ])
+}
+
+/// Absences where the reason of report 103 is unknown
+pub fn malformed_absences_server() -> StubServer {
+    StubServer::start(vec![
+        ("/api/v2/auth/login", Response::fixture("login.json")),
+        ("/api/v2/pull/all", Response::fixture("pull_all.json")),
+        (
+            "/api/v2/reporttypes/reports",
+            Response::fixture("reports_absences_malformed.json"),
+        ),
+    ])
+}
+
+/// Stub server serving the fire operation reports of the fixtures
+pub fn fire_operation_server() -> StubServer {
+    StubServer::start(vec![
+        ("/api/v2/auth/login", Response::fixture("login.json")),
+        ("/api/v2/pull/all", Response::fixture("pull_all.json")),
+        (
+            "/api/v2/reporttypes/reports?id=11146",
+            Response::fixture("reports_fire_operation.json"),
+        ),
+    ])
+}
+
+/// Stub server serving the absence and the fire operation reports of the fixtures
+pub fn reports_server() -> StubServer {
+    StubServer::start(vec![
+        ("/api/v2/auth/login", Response::fixture("login.json")),
+        ("/api/v2/pull/all", Response::fixture("pull_all.json")),
+        (
+            "/api/v2/reporttypes/reports?id=10538",
+            Response::fixture("reports_absences.json"),
+        ),
+        (
+            "/api/v2/reporttypes/reports?id=11146",
+            Response::fixture("reports_fire_operation.json"),
+        ),
+    ])
+}
+
+pub fn fixture(name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name);
+    fs::read_to_string(&path).unwrap_or_else(|_| panic!("Failed to read fixture {path:?}"))
+}
+
+/// Writes a config pointing to the stub server into a temporary directory
+pub fn write_config(dir: &TempDir, divera_url: &str) -> PathBuf {
+    write_config_with(dir, divera_url, "")
+}
+
+/// Writes a config pointing to the stub server with additional sections
+pub fn write_config_with(dir: &TempDir, divera_url: &str, extra: &str) -> PathBuf {
+    write_config_with_divera(dir, divera_url, "", extra)
+}
+
+/// Writes a config pointing to the stub server with additional divera settings and sections
+pub fn write_config_with_divera(
+    dir: &TempDir,
+    divera_url: &str,
+    divera: &str,
+    extra: &str,
+) -> PathBuf {
+    let path = dir.path().join("config.toml");
+    let config = format!(
+        r#"
+[divera]
+username = "user"
+password = "secret"
+url = "{divera_url}"
+{divera}
+
+[webdav]
+username = "user"
+password = "secret"
+root_directory = "http://127.0.0.1:1/remote.php/dav/files/user"
+
+{extra}
+"#
+    );
+    fs::write(&path, config).unwrap();
+    path
+}
+
+pub fn run(config: &Path, args: &[&str]) -> Output {
+    run_with_env(config, args, &[])
+}
+
+/// Runs the binary with the lines written to its stdin
+pub fn run_with_input(config: &Path, args: &[&str], lines: &[&str]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_divera-reports"))
+        .arg("--config")
+        .arg(config)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to run divera-reports");
+    let mut stdin = child.stdin.take().unwrap();
+    for line in lines {
+        writeln!(stdin, "{line}").unwrap();
+    }
+    drop(stdin);
+    child
+        .wait_with_output()
+        .expect("Failed to run divera-reports")
+}
+
+pub fn run_with_env(config: &Path, args: &[&str], envs: &[(&str, &str)]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_divera-reports"))
+        .arg("--config")
+        .arg(config)
+        .args(args)
+        .envs(envs.iter().copied())
+        .output()
+        .expect("Failed to run divera-reports")
+}
@@ -0,0 +1,649 @@
+mod common;
+
+use std::{
+    fs,
+    time::{Duration, Instant},
+};
+
+use common::{
+    absences_server, fixture, reports_server, run, run_with_env, write_config, write_config_with,
+    write_config_with_divera, Response, StubServer,
+};
+use divera_reports::config::Config;
+use tempfile::tempdir;
+
+#[test]
+fn report_types_are_printed() {
+    let server = StubServer::start(vec![
+        ("/api/v2/auth/login", Response::fixture("login.json")),
+        (
+            "/api/v2/reporttypes",
+            Response::fixture("report_types.json"),
+        ),
+    ]);
+    let dir = tempdir().unwrap();
+    let config = write_config(&dir, &server.url);
+
+    let output = run(&config, &["report-types"]);
+
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("10538"));
+    assert!(stdout.contains("Abwesenheit"));
+
+    let requests = server.requests();
+    let login = requests
+        .iter()
+        .find(|request| request.path == "/api/v2/auth/login")
+        .unwrap();
+    assert_eq!(login.method, "POST");
+    assert!(login.body.contains(r#""username":"user""#));
+    let report_types = requests
+        .iter()
+        .find(|request| request.path == "/api/v2/reporttypes")
+        .unwrap();
+    assert!(report_types.query.contains("accesskey=stub-access-token"));
+}
+
+#[test]
+fn absences_are_printed() {
+    let server = absences_server();
+    let dir = tempdir().unwrap();
+    let config = write_config(&dir, &server.url);
+
+    let output = run(&config, &["report", "absences", "--print"]);
+
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Mustermann, Max"));
+    assert!(stdout.contains("2025-10-05"));
+    assert!(stdout.contains("Urlaub"));
+    assert!(stdout.contains("Musterfrau, Erika"));
+    assert!(stdout.contains("Krankheit"));
+
+    let requests = server.requests();
+    let reports = requests
+        .iter()
+        .find(|request| request.path == "/api/v2/reporttypes/reports")
+        .unwrap();
+    assert!(reports.query.contains("id=10538"));
+}
+
+#[test]
+fn all_reports_are_combined() {
+    let server = reports_server();
+    let dir = tempdir().unwrap();
+    let config = write_config_with(
+        &dir,
+        &server.url,
+        "[reports]\nenabled = [\"absences\"]\ngeneric = [11146]",
+    );
+    let output_dir = dir.path().to_string_lossy().to_string();
+
+    let output = run(
+        &config,
+        &[
+            "report",
+            "all",
+            "--write",
+            &output_dir,
+            "--combined",
+            "reports.xlsx",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    assert!(dir.path().join("reports.xlsx").exists());
+    let logins = server
+        .requests()
+        .iter()
+        .filter(|request| request.path == "/api/v2/auth/login")
+        .count();
+    assert_eq!(logins, 1);
+}
+
+#[test]
+fn report_types_with_long_names_are_combined() {
+    let pull_all = fixture("pull_all.json")
+        .replace(
+            "\"Abwesenheit\"",
+            "\"Berichte der Freiwilligen Feuerwehr: Abwesenheit\"",
+        )
+        .replace(
+            "\"Atemschutz Kurzbericht\"",
+            "\"Berichte der Freiwilligen Feuerwehr: Atemschutz\"",
+        );
+    let server = StubServer::start(vec![
+        ("/api/v2/auth/login", Response::fixture("login.json")),
+        ("/api/v2/pull/all", Response::new(200, &pull_all)),
+        (
+            "/api/v2/reporttypes/reports?id=10538",
+            Response::fixture("reports_absences.json"),
+        ),
+        (
+            "/api/v2/reporttypes/reports?id=11146",
+            Response::fixture("reports_fire_operation.json"),
+        ),
+    ]);
+    let dir = tempdir().unwrap();
+    let config = write_config_with(
+        &dir,
+        &server.url,
+        "[reports]\nenabled = []\ngeneric = [10538, 11146]",
+    );
+    let output_dir = dir.path().to_string_lossy().to_string();
+
+    let output = run(
+        &config,
+        &[
+            "report",
+            "all",
+            "--write",
+            &output_dir,
+            "--combined",
+            "reports.xlsx",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    assert!(dir.path().join("reports.xlsx").exists());
+}
+
+#[test]
+fn report_kinds_enabled_twice_are_rejected() {
+    let server = reports_server();
+    let dir = tempdir().unwrap();
+    let config = write_config_with(
+        &dir,
+        &server.url,
+        "[reports]\nenabled = [\"absences\", \"absences\"]",
+    );
+    let output_dir = dir.path().to_string_lossy().to_string();
+
+    let output = run(&config, &["report", "all", "--write", &output_dir]);
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Report absences is enabled more than once"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn all_reports_keep_the_names_of_the_former_services() {
+    let server = reports_server();
+    let dir = tempdir().unwrap();
+    let config = write_config_with(
+        &dir,
+        &server.url,
+        "[reports]\nenabled = [\"absences\", \"fire-operation\"]\ngeneric = [11146]",
+    );
+    let output_dir = dir.path().to_string_lossy().to_string();
+
+    let output = run(&config, &["report", "all", "--write", &output_dir]);
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Report type 11146 is exported as fire-operation and as generic report"),
+        "{stderr}"
+    );
+
+    let config = write_config_with(
+        &dir,
+        &server.url,
+        "[reports]\nenabled = [\"absences\", \"fire-operation\"]",
+    );
+    let output = run(&config, &["report", "all", "--write", &output_dir]);
+
+    assert!(output.status.success(), "{output:?}");
+    assert!(dir.path().join("Abwesenheiten.xlsx").exists());
+    assert!(dir.path().join("Atemschutz_Kurzbericht.xlsx").exists());
+}
+
+#[test]
+fn unsuccessful_response_fails() {
+    let server = StubServer::start(vec![(
+        "/api/v2/auth/login",
+        Response::fixture("login_failed.json"),
+    )]);
+    let dir = tempdir().unwrap();
+    let config = write_config(&dir, &server.url);
+
+    let output = run(&config, &["report-types"]);
+
+    assert_eq!(output.status.code(), Some(77), "{output:?}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Benutzername oder Passwort falsch"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn invalid_proxy_is_no_temporary_failure() {
+    let dir = tempdir().unwrap();
+    let config = write_config_with_divera(&dir, "http://127.0.0.1:1", "proxy = \"::\"", "");
+
+    let output = run(&config, &["report-types"]);
+
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+}
+
+#[test]
+fn error_status_fails() {
+    let server = StubServer::start(vec![
+        ("/api/v2/auth/login", Response::fixture("login.json")),
+        ("/api/v2/reporttypes", Response::new(500, "{}")),
+    ]);
+    let dir = tempdir().unwrap();
+    let config = write_config_with_divera(&dir, &server.url, FAST_RETRIES, "");
+
+    let output = run(&config, &["report-types"]);
+
+    assert_eq!(output.status.code(), Some(75), "{output:?}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("500"), "{stderr}");
+}
+
+#[test]
+fn unexpected_response_reports_path() {
+    let server = StubServer::start(vec![
+        ("/api/v2/auth/login", Response::fixture("login.json")),
+        (
+            "/api/v2/reporttypes",
+            Response::new(
+                200,
+                r#"{"success": true, "data": {"items": {"1": {"name": 1}}}}"#,
+            ),
+        ),
+    ]);
+    let dir = tempdir().unwrap();
+    let config = write_config(&dir, &server.url);
+
+    let output = run(&config, &["report-types"]);
+
+    assert_eq!(output.status.code(), Some(65), "{output:?}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("data.items.1"), "{stderr}");
+}
+
+/// Retries with short delays so the tests stay fast
+const FAST_RETRIES: &str = "retries = 2\nbackoff_millis = 10";
+
+#[test]
+fn transient_errors_are_retried() {
+    let server = StubServer::start(vec![
+        ("/api/v2/auth/login", Response::fixture("login.json")),
+        ("/api/v2/pull/all", Response::new(503, "{}")),
+        ("/api/v2/pull/all", Response::new(502, "{}")),
+        ("/api/v2/pull/all", Response::fixture("pull_all.json")),
+        (
+            "/api/v2/reporttypes/reports",
+            Response::fixture("reports_absences.json"),
+        ),
+    ]);
+    let dir = tempdir().unwrap();
+    let config = write_config_with_divera(&dir, &server.url, FAST_RETRIES, "");
+
+    let output = run(&config, &["report", "absences", "--print"]);
+
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(server.requests_to("/api/v2/pull/all").len(), 3);
+}
+
+#[test]
+fn interrupted_responses_are_retried() {
+    let server = StubServer::start(vec![
+        ("/api/v2/auth/login", Response::fixture("login.json")),
+        (
+            "/api/v2/reporttypes",
+            Response::fixture("report_types.json").truncated(),
+        ),
+        (
+            "/api/v2/reporttypes",
+            Response::fixture("report_types.json"),
+        ),
+    ]);
+    let dir = tempdir().unwrap();
+    let config = write_config_with_divera(&dir, &server.url, FAST_RETRIES, "");
+
+    let output = run(&config, &["report-types"]);
+
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(server.requests_to("/api/v2/reporttypes").len(), 2);
+}
+
+#[test]
+fn retries_give_up_after_limit() {
+    let server = StubServer::start(vec![
+        ("/api/v2/auth/login", Response::fixture("login.json")),
+        ("/api/v2/reporttypes", Response::new(500, "{}")),
+    ]);
+    let dir = tempdir().unwrap();
+    let config = write_config_with_divera(&dir, &server.url, FAST_RETRIES, "");
+
+    let output = run(&config, &["report-types"]);
+
+    assert_eq!(output.status.code(), Some(75), "{output:?}");
+    assert_eq!(server.requests_to("/api/v2/reporttypes").len(), 3);
+}
+
+#[test]
+fn retry_after_is_respected() {
+    let server = StubServer::start(vec![
+        ("/api/v2/auth/login", Response::fixture("login.json")),
+        (
+            "/api/v2/reporttypes",
+            Response::new(429, "{}").with_header("Retry-After", "1"),
+        ),
+        (
+            "/api/v2/reporttypes",
+            Response::fixture("report_types.json"),
+        ),
+    ]);
+    let dir = tempdir().unwrap();
+    let config = write_config_with_divera(&dir, &server.url, FAST_RETRIES, "");
+
+    let start = Instant::now();
+    let output = run(&config, &["report-types"]);
+
+    assert!(output.status.success(), "{output:?}");
+    assert!(start.elapsed() >= Duration::from_secs(1));
+    assert_eq!(server.requests_to("/api/v2/reporttypes").len(), 2);
+}
+
+#[test]
+fn login_is_not_retried() {
+    let server = StubServer::start(vec![("/api/v2/auth/login", Response::new(503, "{}"))]);
+    let dir = tempdir().unwrap();
+    let config = write_config_with_divera(&dir, &server.url, FAST_RETRIES, "");
+
+    let output = run(&config, &["report-types"]);
+
+    assert_eq!(output.status.code(), Some(75), "{output:?}");
+    assert_eq!(server.requests_to("/api/v2/auth/login").len(), 1);
+}
+
+#[test]
+fn slow_responses_time_out() {
+    let server = StubServer::start(vec![
+        ("/api/v2/auth/login", Response::fixture("login.json")),
+        (
+            "/api/v2/reporttypes",
+            Response::new(200, &fixture("report_types.json")).with_delay(Duration::from_secs(3)),
+        ),
+    ]);
+    let dir = tempdir().unwrap();
+    let config = write_config_with_divera(&dir, &server.url, "timeout_secs = 1\nretries = 0", "");
+
+    let start = Instant::now();
+    let output = run(&config, &["report-types"]);
+
+    assert_eq!(output.status.code(), Some(75), "{output:?}");
+    assert!(start.elapsed() < Duration::from_secs(3));
+}
+
+#[test]
+fn configured_user_agent_is_sent() {
+    let server = StubServer::start(vec![
+        ("/api/v2/auth/login", Response::fixture("login.json")),
+        (
+            "/api/v2/reporttypes",
+            Response::fixture("report_types.json"),
+        ),
+    ]);
+    let dir = tempdir().unwrap();
+    let config = write_config_with_divera(&dir, &server.url, r#"user_agent = "feuerwehr/1.0""#, "");
+
+    let output = run(&config, &["report-types"]);
+
+    assert!(output.status.success(), "{output:?}");
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    for request in requests {
+        assert_eq!(request.header("user-agent"), Some("feuerwehr/1.0"));
+    }
+}
+
+fn token_cache_server(jwt: Response) -> StubServer {
+    StubServer::start(vec![
+        ("/api/v2/auth/login", Response::fixture("login.json")),
+        ("/api/v2/auth/jwt", jwt),
+        (
+            "/api/v2/reporttypes",
+            Response::fixture("report_types.json"),
+        ),
+    ])
+}
+
+#[test]
+fn access_token_is_cached_between_runs() {
+    let server = token_cache_server(Response::fixture("jwt.json"));
+    let dir = tempdir().unwrap();
+    let cache = dir.path().join("token.json");
+    let settings = format!("token_cache = {cache:?}");
+    let config = write_config_with_divera(&dir, &server.url, &settings, "");
+
+    let first = run(&config, &["report-types"]);
+    let second = run(&config, &["report-types"]);
+
+    assert!(first.status.success(), "{first:?}");
+    assert!(second.status.success(), "{second:?}");
+    assert_eq!(server.requests_to("/api/v2/auth/login").len(), 1);
+    let jwt = server.requests_to("/api/v2/auth/jwt");
+    assert_eq!(jwt.len(), 1);
+    assert!(jwt[0].query.contains("accesskey=stub-access-token"));
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(&cache).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
+
+#[test]
+fn rejected_cached_token_falls_back_to_login() {
+    let server = token_cache_server(Response::new(401, "{}"));
+    let dir = tempdir().unwrap();
+    let cache = dir.path().join("token.json");
+    let url = server.url.clone();
+    fs::write(
+        &cache,
+        format!(r#"{{"url":"{url}","username":"user","access_token":"expired"}}"#),
+    )
+    .unwrap();
+    let settings = format!("token_cache = {cache:?}");
+    let config = write_config_with_divera(&dir, &server.url, &settings, "");
+
+    let output = run(&config, &["report-types"]);
+
+    assert!(output.status.success(), "{output:?}");
+    let paths: Vec<String> = server
+        .requests()
+        .into_iter()
+        .map(|request| request.path)
+        .collect();
+    let jwt = paths.iter().position(|path| path == "/api/v2/auth/jwt");
+    let login = paths.iter().position(|path| path == "/api/v2/auth/login");
+    assert!(jwt.is_some() && jwt < login, "{paths:?}");
+    assert!(server.requests_to("/api/v2/auth/jwt")[0]
+        .query
+        .contains("accesskey=expired"));
+    assert_eq!(server.requests_to("/api/v2/auth/login").len(), 1);
+    assert!(fs::read_to_string(&cache)
+        .unwrap()
+        .contains("stub-access-token"));
+}
+
+/// Writes a config with the given divera section and a dummy webdav section
+fn write_divera_config(dir: &tempfile::TempDir, divera: &str) -> std::path::PathBuf {
+    let path = dir.path().join("config.toml");
+    let config = format!(
+        "[divera]\n{divera}\n\n[webdav]\nusername = \"user\"\npassword = \"secret\"\nroot_directory = \"http://127.0.0.1:1/\"\n"
+    );
+    fs::write(&path, config).unwrap();
+    path
+}
+
+#[test]
+fn access_key_skips_login() {
+    let server = StubServer::start(vec![(
+        "/api/v2/reporttypes",
+        Response::fixture("report_types.json"),
+    )]);
+    let dir = tempdir().unwrap();
+    let url = &server.url;
+    let config = write_divera_config(
+        &dir,
+        &format!("access_key = \"cluster-key\"\nurl = \"{url}\""),
+    );
+
+    let output = run(&config, &["report-types"]);
+
+    assert!(output.status.success(), "{output:?}");
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert!(requests[0].query.contains("accesskey=cluster-key"));
+}
+
+#[test]
+fn multiple_authentication_methods_are_rejected() {
+    let dir = tempdir().unwrap();
+    let config = write_divera_config(
+        &dir,
+        "username = \"user\"\npassword = \"secret\"\naccess_key = \"cluster-key\"",
+    );
+
+    let output = run(&config, &["report-types"]);
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("either an access key"), "{stderr}");
+}
+
+#[test]
+fn init_accepts_access_key() {
+    let dir = tempdir().unwrap();
+    let config = dir.path().join("config.toml");
+    let webdav = [
+        "--webdav-username",
+        "user",
+        "--webdav-password",
+        "secret",
+        "--webdav-directory",
+        "http://127.0.0.1:1/",
+    ];
+
+    let mut both = vec![
+        "init",
+        "--divera-access-key",
+        "key",
+        "--divera-username",
+        "user",
+    ];
+    both.extend(webdav);
+    let output = run(&config, &both);
+    assert!(!output.status.success());
+    assert!(!config.exists());
+
+    let mut access_key = vec!["init", "--divera-access-key", "cluster-key"];
+    access_key.extend(webdav);
+    let output = run(&config, &access_key);
+    assert!(output.status.success(), "{output:?}");
+    let divera = Config::read(&config).unwrap().divera;
+    assert_eq!(divera.access_key.as_deref(), Some("cluster-key"));
+    assert_eq!(divera.username, None);
+    assert!(!divera.password.is_set());
+}
+
+fn login_server() -> StubServer {
+    StubServer::start(vec![
+        ("/api/v2/auth/login", Response::fixture("login.json")),
+        (
+            "/api/v2/reporttypes",
+            Response::fixture("report_types.json"),
+        ),
+    ])
+}
+
+fn login_password(server: &StubServer) -> String {
+    let login = &server.requests_to("/api/v2/auth/login")[0];
+    let body: serde_json::Value = serde_json::from_str(&login.body).unwrap();
+    body["Login"]["password"].as_str().unwrap().to_string()
+}
+
+#[test]
+fn password_is_read_from_file() {
+    let server = login_server();
+    let dir = tempdir().unwrap();
+    let secret = dir.path().join("divera-password");
+    fs::write(&secret, "from-file\n").unwrap();
+    let url = &server.url;
+    let config = write_divera_config(
+        &dir,
+        &format!("username = \"user\"\npassword_file = {secret:?}\nurl = \"{url}\""),
+    );
+
+    let output = run(&config, &["report-types"]);
+
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(login_password(&server), "from-file");
+}
+
+#[test]
+fn password_is_read_from_environment() {
+    let server = login_server();
+    let dir = tempdir().unwrap();
+    let url = &server.url;
+    let config = write_divera_config(
+        &dir,
+        &format!("username = \"user\"\npassword_env = \"DIVERA_PASSWORD\"\nurl = \"{url}\""),
+    );
+
+    let output = run_with_env(
+        &config,
+        &["report-types"],
+        &[("DIVERA_PASSWORD", "from-env")],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(login_password(&server), "from-env");
+}
+
+#[test]
+fn password_is_read_from_command() {
+    let server = login_server();
+    let dir = tempdir().unwrap();
+    let url = &server.url;
+    let config = write_divera_config(
+        &dir,
+        &format!("username = \"user\"\npassword_command = \"echo from-command\"\nurl = \"{url}\""),
+    );
+
+    let output = run(&config, &["report-types"]);
+
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(login_password(&server), "from-command");
+}
+
+#[test]
+fn multiple_password_sources_are_rejected() {
+    let server = login_server();
+    let dir = tempdir().unwrap();
+    let url = &server.url;
+    let config = write_divera_config(
+        &dir,
+        &format!(
+            "username = \"user\"\npassword = \"secret\"\npassword_env = \"HOME\"\nurl = \"{url}\""
+        ),
+    );
+
+    let output = run(&config, &["report-types"]);
+
+    assert!(!output.status.success());
+    assert!(server.requests().is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Only one of password"), "{stderr}");
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use common::{run_with_input, Response, StubServer};
+use divera_reports::config::{Config, ReportKind};
+use tempfile::tempdir;
+
+const WEBDAV_PATH: &str = "/remote.php/dav/files/user";
+
+fn init_server(webdav: Response) -> StubServer {
+    StubServer::start(vec![
+        ("/api/v2/auth/login", Response::fixture("login.json")),
+        (
+            "/api/v2/reporttypes",
+            Response::fixture("report_types.json"),
+        ),
+        (WEBDAV_PATH, webdav),
+    ])
+}
+
+fn answers<'a>(server: &'a StubServer, webdav_directory: &'a str) -> Vec<&'a str> {
+    vec![
+        &server.url,
+        "",
+        "user",
+        "secret",
+        "webdav-user",
+        "webdav-secret",
+        webdav_directory,
+    ]
+}
+
+#[test]
+fn interactive_init_checks_connections_and_selects_reports() {
+    let server = init_server(Response::new(207, ""));
+    let webdav_directory = server.url.replace("/api/", WEBDAV_PATH);
+    let dir = tempdir().unwrap();
+    let config = dir.path().join("config.toml");
+    let mut input = answers(&server, &webdav_directory);
+    input.extend(["y", "n", "11146"]);
+
+    let output = run_with_input(&config, &["init", "--interactive"], &input);
+
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Atemschutz Kurzbericht"), "{stdout}");
+    let webdav = server.requests_to(WEBDAV_PATH);
+    assert_eq!(webdav.len(), 1);
+    assert_eq!(webdav[0].method, "PROPFIND");
+
+    let written = Config::read(&config).unwrap();
+    assert_eq!(written.divera.url, server.url);
+    assert_eq!(written.divera.username.as_deref(), Some("user"));
+    assert_eq!(written.webdav.as_ref().unwrap().username, "webdav-user");
+    assert_eq!(written.reports.enabled, vec![ReportKind::Absences]);
+    assert_eq!(written.reports.generic, vec![11146]);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&config).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
+
+#[test]
+fn interactive_init_does_not_write_config_on_failed_check() {
+    let server = init_server(Response::new(401, ""));
+    let webdav_directory = server.url.replace("/api/", WEBDAV_PATH);
+    let dir = tempdir().unwrap();
+    let config = dir.path().join("config.toml");
+    let input = answers(&server, &webdav_directory);
+
+    let output = run_with_input(&config, &["init", "--interactive"], &input);
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("WebDAV connection failed"), "{stderr}");
+    assert!(!config.exists());
+}
+
+#[test]
+fn interactive_init_asks_for_the_password_of_a_given_username() {
+    let server = init_server(Response::new(207, ""));
+    let webdav_directory = server.url.replace("/api/", WEBDAV_PATH);
+    let dir = tempdir().unwrap();
+    let config = dir.path().join("config.toml");
+    let input = [
+        server.url.as_str(),
+        "secret",
+        "webdav-user",
+        "webdav-secret",
+        &webdav_directory,
+        "y",
+        "n",
+        "",
+    ];
+
+    let output = run_with_input(
+        &config,
+        &["init", "--interactive", "--divera-username", "user"],
+        &input,
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    let login = server.requests_to("/api/v2/auth/login");
+    assert_eq!(login.len(), 1);
+    assert!(login[0].body.contains("secret"), "{}", login[0].body);
+    let written = Config::read(&config).unwrap();
+    assert_eq!(written.divera.username.as_deref(), Some("user"));
+
+    let other = dir.path().join("other.toml");
+    let output = run_with_input(
+        &other,
+        &[
+            "init",
+            "--divera-username",
+            "user",
+            "--webdav-username",
+            "webdav-user",
+            "--webdav-password",
+            "webdav-secret",
+            "--webdav-directory",
+            &webdav_directory,
+        ],
+        &[],
+    );
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--divera-password"), "{stderr}");
+    assert!(!other.exists());
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use common::absences_server;
+use divera_reports::{
+    divera::Settings,
+    reports::absent::{default_mapping, AbsentReport, Reason},
+    DiveraClient, DiveraError, Parsed, Reports,
+};
+
+#[test]
+fn absences_are_fetched_with_the_library() {
+    let server = absences_server();
+    let mut client = DiveraClient::new(&server.url, Settings::default()).unwrap();
+    client.login("user", "secret").unwrap();
+
+    let all = client.pull_all().unwrap();
+    let report_type = &all.cluster.reporttypes.items[&10538];
+    let reports = client.reports(10538).unwrap();
+    let mapping = default_mapping();
+    let parsed: Parsed<Vec<AbsentReport>> =
+        Reports::new_from_reports(report_type, reports, &all.cluster.consumer, &mapping, false)
+            .unwrap();
+
+    assert!(parsed.errors.is_empty());
+    assert_eq!(parsed.reports.len(), 2);
+    assert_eq!(parsed.reports[1].user, "Musterfrau, Erika");
+    assert!(matches!(parsed.reports[1].reason, Reason::Illness));
+}
+
+#[test]
+fn requests_without_login_fail() {
+    let server = absences_server();
+    let client = DiveraClient::new(&server.url, Settings::default()).unwrap();
+
+    let error = client.pull_all().unwrap_err();
+
+    assert!(matches!(error, DiveraError::MissingAccessToken));
+    assert!(server.requests().is_empty());
+}
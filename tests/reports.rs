@@ -0,0 +1,726 @@
+mod common;
+
+use std::fs;
+
+use chrono::Local;
+use common::{
+    absences_server, fire_operation_server, fixture, malformed_absences_server, run, write_config,
+    write_config_with, Response, StubServer,
+};
+use divera_reports::config::{Config, ReportKind};
+use serde_json::{json, Value};
+use tempfile::tempdir;
+
+#[test]
+fn absences_are_printed_as_json() {
+    let server = absences_server();
+    let dir = tempdir().unwrap();
+    let config = write_config(&dir, &server.url);
+
+    let output = run(
+        &config,
+        &["report", "absences", "--print", "--format", "json"],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    let reports: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        reports[0],
+        json!({
+            "id": 101,
+            "user": "Mustermann, Max",
+            "begin": "2025-10-05",
+            "end": "2025-10-11",
+            "reason": "vacation",
+            "note": "Sommerurlaub",
+        })
+    );
+}
+
+#[test]
+fn partial_mappings_keep_the_defaults() {
+    let server = absences_server();
+    let dir = tempdir().unwrap();
+    let mapping = "[mapping.absences]\nreport_type = 10538\n\n\
+                   [mapping.roster.options.time_scope]\nfull = \"custom-full\"\n";
+    let config = write_config_with(&dir, &server.url, mapping);
+
+    let output = run(
+        &config,
+        &["report", "absences", "--print", "--format", "json"],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    let reports: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(reports[0]["reason"], "vacation");
+    assert_eq!(reports[0]["note"], "Sommerurlaub");
+
+    let mappings = Config::read(&config).unwrap().mapping;
+    let roster = mappings.get(ReportKind::Roster);
+    assert_eq!(roster.options["time_scope"]["full"], "custom-full");
+    assert!(roster.options["time_scope"].len() > 1);
+    assert!(!roster.fields.is_empty());
+}
+
+#[test]
+fn absences_are_written_as_ndjson() {
+    let server = absences_server();
+    let dir = tempdir().unwrap();
+    let config = write_config(&dir, &server.url);
+    let path = dir.path().join("absences.ndjson");
+
+    let output = run(
+        &config,
+        &[
+            "report",
+            "absences",
+            "--write",
+            path.to_str().unwrap(),
+            "--format",
+            "ndjson",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    let content = fs::read_to_string(path).unwrap();
+    let lines: Vec<Value> = content
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[1]["reason"], "illness");
+}
+
+#[test]
+fn absences_are_written_as_csv() {
+    let server = absences_server();
+    let dir = tempdir().unwrap();
+    let config = write_config(&dir, &server.url);
+    let path = dir.path().join("absences.csv");
+
+    let output = run(
+        &config,
+        &[
+            "report",
+            "absences",
+            "--write",
+            path.to_str().unwrap(),
+            "--format",
+            "csv",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    let content = fs::read_to_string(path).unwrap();
+    let mut lines = content.lines();
+    assert_eq!(lines.next(), Some("id,user,begin,end,reason,note"));
+    assert_eq!(
+        lines.next(),
+        Some("101,\"Mustermann, Max\",2025-10-05,2025-10-11,vacation,Sommerurlaub")
+    );
+}
+
+#[test]
+fn absences_are_filtered_by_date() {
+    let server = absences_server();
+    let dir = tempdir().unwrap();
+    let config = write_config(&dir, &server.url);
+
+    let output = run(
+        &config,
+        &[
+            "report",
+            "absences",
+            "--print",
+            "--format",
+            "json",
+            "--from",
+            "2025-10-11",
+            "--to",
+            "2025-10-13",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    let reports: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let ids: Vec<&Value> = reports
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|report| &report["id"])
+        .collect();
+    assert_eq!(ids, vec![&json!(101)]);
+
+    let output = run(
+        &config,
+        &[
+            "report", "absences", "--print", "--format", "json", "--year", "2024",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    let reports: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(reports, json!([]));
+}
+
+#[test]
+fn empty_reports_are_written_as_xlsx() {
+    let server = absences_server();
+    let dir = tempdir().unwrap();
+    let config = write_config(&dir, &server.url);
+    let path = dir.path().join("Abwesenheiten.xlsx");
+
+    let output = run(
+        &config,
+        &[
+            "report",
+            "absences",
+            "--write",
+            path.to_str().unwrap(),
+            "--year",
+            "1999",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    // Xlsx files are zip archives
+    assert!(fs::read(&path).unwrap().starts_with(b"PK"));
+}
+
+#[test]
+fn empty_reports_are_written_as_csv_with_header() {
+    let server = absences_server();
+    let dir = tempdir().unwrap();
+    let config = write_config(&dir, &server.url);
+    let path = dir.path().join("absences.csv");
+
+    let output = run(
+        &config,
+        &[
+            "report",
+            "absences",
+            "--write",
+            path.to_str().unwrap(),
+            "--format",
+            "csv",
+            "--year",
+            "1999",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    let content = fs::read_to_string(path).unwrap();
+    assert_eq!(content, "id,user,begin,end,reason,note\n");
+}
+
+#[test]
+fn absences_are_written_as_icalendar() {
+    let server = absences_server();
+    let dir = tempdir().unwrap();
+    let config = write_config(&dir, &server.url);
+    let path = dir.path().join("absences.ics");
+
+    let output = run(
+        &config,
+        &[
+            "report",
+            "absences",
+            "--write",
+            path.to_str().unwrap(),
+            "--format",
+            "ics",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    let content = fs::read_to_string(path).unwrap();
+    assert!(content.starts_with("BEGIN:VCALENDAR\r\n"));
+    assert!(content.ends_with("END:VCALENDAR\r\n"));
+    assert_eq!(content.matches("BEGIN:VEVENT").count(), 2);
+    assert!(content.contains("UID:absence-101@divera-reports\r\n"));
+    assert!(content.contains("DTSTART;VALUE=DATE:20251005\r\n"));
+    assert!(content.contains("DTEND;VALUE=DATE:20251012\r\n"));
+    assert!(content.contains("SUMMARY:Mustermann\\, Max: Urlaub\r\n"));
+}
+
+#[test]
+fn reports_are_printed_by_the_id_of_their_type() {
+    let server = absences_server();
+    let dir = tempdir().unwrap();
+    let config = write_config(&dir, &server.url);
+
+    let output = run(
+        &config,
+        &["report", "by-id", "10538", "--print", "--format", "json"],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    let reports: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let keys: Vec<&String> = reports[0].as_object().unwrap().keys().collect();
+    assert_eq!(keys, ["id", "user", "Von", "Bis", "Bemerkung", "Grund"]);
+    assert_eq!(reports[0]["Grund"], json!(["Urlaub"]));
+    assert_eq!(reports[0]["Bemerkung"], "Sommerurlaub");
+    // The note of the second report is empty
+    assert_eq!(reports[1]["id"], 102);
+    assert_eq!(reports[1]["Bemerkung"], Value::Null);
+    assert_eq!(
+        reports[1]["Grund"],
+        json!(["Krankheit / Dienstunfähigkeit"])
+    );
+}
+
+#[test]
+fn short_reports_of_a_type_with_duplicate_field_names_are_written_as_csv() {
+    let pull_all = fixture("pull_all.json").replace("\"Bis\"", "\"Von\"");
+    let mut reports: Value = serde_json::from_str(&fixture("reports_absences.json")).unwrap();
+    // Report created before the note and reason were added to the report type
+    reports["data"]["items"][1]["fields"]
+        .as_array_mut()
+        .unwrap()
+        .truncate(2);
+    let server = StubServer::start(vec![
+        ("/api/v2/auth/login", Response::fixture("login.json")),
+        ("/api/v2/pull/all", Response::new(200, &pull_all)),
+        (
+            "/api/v2/reporttypes/reports",
+            Response::new(200, &reports.to_string()),
+        ),
+    ]);
+    let dir = tempdir().unwrap();
+    let config = write_config(&dir, &server.url);
+    let path = dir.path().join("reports.csv");
+
+    let output = run(
+        &config,
+        &[
+            "report",
+            "by-id",
+            "10538",
+            "--write",
+            path.to_str().unwrap(),
+            "--format",
+            "csv",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    let content = fs::read_to_string(path).unwrap();
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(
+        lines[0],
+        "id,user,Von (10f05309-e584-4470-a0db-ce6bb15ade34),\
+         Von (a9246571-63fd-4cdf-b6f1-77d93173b362),Bemerkung,Grund"
+    );
+    assert_eq!(
+        lines[2],
+        "102,\"Musterfrau, Erika\",2025-10-14,2025-10-15,,"
+    );
+}
+
+#[test]
+fn icalendar_is_only_supported_for_absences() {
+    let server = absences_server();
+    let dir = tempdir().unwrap();
+    let config = write_config(&dir, &server.url);
+
+    let output = run(
+        &config,
+        &["report", "by-id", "10538", "--print", "--format", "ics"],
+    );
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("iCalendar"), "{stderr}");
+}
+
+#[test]
+fn absence_coverage_is_computed_per_day() {
+    let server = absences_server();
+    let dir = tempdir().unwrap();
+    let config = write_config(&dir, &server.url);
+
+    let output = run(
+        &config,
+        &[
+            "report",
+            "coverage",
+            "--print",
+            "--format",
+            "json",
+            "--from",
+            "2025-10-11",
+            "--to",
+            "2025-10-14",
+            "--minimum-strength",
+            "2",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    let days: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        days,
+        json!([
+            {"date": "2025-10-11", "absent": 1, "available": 1, "below_minimum": true, "absent_members": ["Mustermann, Max"]},
+            {"date": "2025-10-12", "absent": 0, "available": 2, "below_minimum": false, "absent_members": []},
+            {"date": "2025-10-13", "absent": 0, "available": 2, "below_minimum": false, "absent_members": []},
+            {"date": "2025-10-14", "absent": 1, "available": 1, "below_minimum": true, "absent_members": ["Musterfrau, Erika"]},
+        ])
+    );
+
+    let path = dir.path().join("coverage.xlsx");
+    let output = run(
+        &config,
+        &[
+            "report",
+            "coverage",
+            "--write",
+            path.to_str().unwrap(),
+            "--last-month",
+        ],
+    );
+    assert!(output.status.success(), "{output:?}");
+    assert!(path.exists());
+}
+
+/// Stub server where both members are named alike and the reports are rewritten by `edit`
+fn namesakes_server(reports: &str, edit: impl FnOnce(&mut Value)) -> StubServer {
+    let pull_all = fixture("pull_all.json").replace("Musterfrau, Erika", "Mustermann, Max");
+    let mut reports: Value = serde_json::from_str(&fixture(reports)).unwrap();
+    edit(&mut reports["data"]["items"]);
+    StubServer::start(vec![
+        ("/api/v2/auth/login", Response::fixture("login.json")),
+        ("/api/v2/pull/all", Response::new(200, &pull_all)),
+        (
+            "/api/v2/reporttypes/reports",
+            Response::new(200, &reports.to_string()),
+        ),
+    ])
+}
+
+#[test]
+fn coverage_counts_members_by_id() {
+    let server = namesakes_server("reports_absences.json", |items| {
+        // Both members and an unknown user are absent on the same days
+        items[1]["fields"] = items[0]["fields"].clone();
+        let mut unknown = items[0].clone();
+        unknown["id"] = json!(104);
+        unknown["user_cluster_relation_id"] = json!(99);
+        items.as_array_mut().unwrap().push(unknown);
+    });
+    let dir = tempdir().unwrap();
+    let config = write_config(&dir, &server.url);
+
+    let output = run(
+        &config,
+        &[
+            "report",
+            "coverage",
+            "--print",
+            "--format",
+            "json",
+            "--from",
+            "2025-10-11",
+            "--to",
+            "2025-10-11",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    let days: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(days[0]["absent"], 2);
+    assert_eq!(days[0]["available"], 0);
+    assert_eq!(
+        days[0]["absent_members"],
+        json!(["Mustermann, Max", "Mustermann, Max"])
+    );
+}
+
+#[test]
+fn fire_operations_are_summarized_per_member_and_year() {
+    let server = fire_operation_server();
+    let dir = tempdir().unwrap();
+    let config = write_config(&dir, &server.url);
+
+    let output = run(
+        &config,
+        &[
+            "report",
+            "fire-operation",
+            "--summary",
+            "--print",
+            "--format",
+            "json",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    let rows: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(rows.as_array().unwrap().len(), 3);
+    assert_eq!(
+        rows[2],
+        json!({
+            "user": "Mustermann, Max",
+            "year": 2025,
+            "operations": 1,
+            "trainings": 1,
+            "duration": 70,
+            "single_bottles": 1,
+            "double_bottles": 1,
+            "cobra": 0,
+            "fire_fighting": 2,
+            "leading": 0,
+            "rescuing": 0,
+            "roof_opening": 0,
+            "ventilation": 1,
+        })
+    );
+
+    let path = dir.path().join("fire_operation.xlsx");
+    let output = run(
+        &config,
+        &[
+            "report",
+            "fire-operation",
+            "--summary",
+            "--write",
+            path.to_str().unwrap(),
+        ],
+    );
+    assert!(output.status.success(), "{output:?}");
+    assert!(path.exists());
+}
+
+#[test]
+fn fire_operations_of_namesakes_are_summarized_separately() {
+    let server = namesakes_server("reports_fire_operation.json", |_| {});
+    let dir = tempdir().unwrap();
+    let config = write_config(&dir, &server.url);
+
+    let output = run(
+        &config,
+        &[
+            "report",
+            "fire-operation",
+            "--summary",
+            "--print",
+            "--format",
+            "json",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    let rows: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let rows_2024: Vec<(&Value, &Value)> = rows
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|row| row["year"] == 2024)
+        .map(|row| (&row["user"], &row["duration"]))
+        .collect();
+    assert_eq!(
+        rows_2024,
+        vec![
+            (&json!("Mustermann, Max"), &json!(25)),
+            (&json!("Mustermann, Max"), &json!(20)),
+        ]
+    );
+}
+
+#[test]
+fn breathing_apparatus_compliance_is_checked() {
+    let server = fire_operation_server();
+    let dir = tempdir().unwrap();
+    let config = write_config(&dir, &server.url);
+
+    let output = run(
+        &config,
+        &[
+            "report",
+            "compliance",
+            "--print",
+            "--format",
+            "json",
+            "--date",
+            "2026-06-01",
+            "--warning-days",
+            "120",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    let entries: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        entries,
+        json!([
+            {
+                "user": "Musterfrau, Erika",
+                "last_date": "2024-10-01",
+                "last_type": "training",
+                "due": "2025-10-01",
+                "status": "overdue",
+            },
+            {
+                "user": "Mustermann, Max",
+                "last_date": "2025-09-07",
+                "last_type": "operation",
+                "due": "2026-09-07",
+                "status": "due_soon",
+            },
+        ])
+    );
+}
+
+#[test]
+fn compliance_of_namesakes_ignores_later_reports() {
+    let server = namesakes_server("reports_fire_operation.json", |_| {});
+    let dir = tempdir().unwrap();
+    let config = write_config(&dir, &server.url);
+
+    let output = run(
+        &config,
+        &[
+            "report",
+            "compliance",
+            "--print",
+            "--format",
+            "json",
+            "--date",
+            "2025-06-01",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    let entries: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let last_dates: Vec<(&Value, &Value)> = entries
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| (&entry["user"], &entry["last_date"]))
+        .collect();
+    assert_eq!(
+        last_dates,
+        vec![
+            (&json!("Mustermann, Max"), &json!("2024-10-01")),
+            (&json!("Mustermann, Max"), &json!("2025-05-01")),
+        ]
+    );
+}
+
+#[test]
+fn malformed_report_aborts_by_default() {
+    let server = malformed_absences_server();
+    let dir = tempdir().unwrap();
+    let config = write_config(&dir, &server.url);
+
+    let output = run(&config, &["report", "absences", "--print"]);
+
+    assert!(!output.status.success(), "{output:?}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Failed to parse report 103"), "{stderr}");
+}
+
+#[test]
+fn malformed_report_is_skipped_in_lenient_mode() {
+    let server = malformed_absences_server();
+    let dir = tempdir().unwrap();
+    let config = write_config(&dir, &server.url);
+
+    let output = run(
+        &config,
+        &["report", "absences", "--print", "--max-errors", "1"],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Sommerurlaub"), "{stdout}");
+    assert!(stdout.contains("Fehler"), "{stdout}");
+    assert!(stdout.contains("103"), "{stdout}");
+}
+
+#[test]
+fn skipped_reports_are_listed_with_the_coverage() {
+    let server = malformed_absences_server();
+    let dir = tempdir().unwrap();
+    let config = write_config(&dir, &server.url);
+
+    let output = run(
+        &config,
+        &[
+            "report",
+            "coverage",
+            "--print",
+            "--from",
+            "2025-10-11",
+            "--to",
+            "2025-10-14",
+            "--max-errors",
+            "1",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Fehler"), "{stdout}");
+    assert!(stdout.contains("103"), "{stdout}");
+}
+
+#[test]
+fn exceeding_the_error_threshold_fails_after_export() {
+    let server = malformed_absences_server();
+    let dir = tempdir().unwrap();
+    let config = write_config(&dir, &server.url);
+    let path = dir.path().join("absences.json");
+
+    let output = run(
+        &config,
+        &[
+            "report",
+            "absences",
+            "--lenient",
+            "--write",
+            path.to_str().unwrap(),
+            "--format",
+            "json",
+        ],
+    );
+
+    assert!(!output.status.success(), "{output:?}");
+    let reports: Value = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+    assert_eq!(reports.as_array().unwrap().len(), 1);
+    assert_eq!(reports[0]["id"], 101);
+}
+
+#[test]
+fn file_names_are_rendered_from_templates() {
+    let server = absences_server();
+    let dir = tempdir().unwrap();
+    let config = write_config(&dir, &server.url);
+    let template = dir.path().join("{title}_{year}-{month}.csv");
+
+    let output = run(
+        &config,
+        &[
+            "report",
+            "absences",
+            "--write",
+            template.to_str().unwrap(),
+            "--format",
+            "csv",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    let today = Local::now().date_naive();
+    let expected = format!("Abwesenheiten_{}.csv", today.format("%Y-%m"));
+    assert!(dir.path().join(expected).exists());
+
+    let template = dir.path().join("{week}.csv");
+    let output = run(
+        &config,
+        &["report", "absences", "--write", template.to_str().unwrap()],
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Unknown placeholder {week}"), "{stderr}");
+}
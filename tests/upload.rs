@@ -0,0 +1,683 @@
+mod common;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::{Days, Local};
+use common::{
+    absences_server, malformed_absences_server, run, write_config_with, Response, SmtpSink,
+    StubServer,
+};
+use sha2::{Digest, Sha256};
+use tempfile::{tempdir, TempDir};
+
+const UPLOAD_CSV: [&str; 6] = [
+    "report",
+    "absences",
+    "--upload",
+    "Abwesenheiten 2025.csv",
+    "--format",
+    "csv",
+];
+
+/// Absences as written by `--write` to compare the uploaded files with
+fn absences_csv(config: &Path, dir: &TempDir) -> String {
+    let path = dir.path().join("expected.csv");
+    let output = run(
+        config,
+        &[
+            "report",
+            "absences",
+            "--write",
+            path.to_str().unwrap(),
+            "--format",
+            "csv",
+        ],
+    );
+    assert!(output.status.success(), "{output:?}");
+    fs::read_to_string(path).unwrap()
+}
+
+#[test]
+fn reports_are_uploaded_to_a_directory_target() {
+    let server = absences_server();
+    let dir = tempdir().unwrap();
+    let share = dir.path().join("share").join("reports");
+    let config = write_config_with(
+        &dir,
+        &server.url,
+        &format!(
+            "[targets.share]\ntype = \"directory\"\npath = \"{}\"\n\n[reports.targets]\ndefault = [\"share\"]\n",
+            share.display()
+        ),
+    );
+
+    let output = run(&config, &UPLOAD_CSV);
+
+    assert!(output.status.success(), "{output:?}");
+    let uploaded = fs::read_to_string(share.join("Abwesenheiten 2025.csv")).unwrap();
+    assert_eq!(uploaded, absences_csv(&config, &dir));
+    assert_eq!(fs::read_dir(&share).unwrap().count(), 1);
+}
+
+#[test]
+fn reports_are_uploaded_into_templated_subdirectories() {
+    let server = absences_server();
+    let dir = tempdir().unwrap();
+    let share = dir.path().join("share");
+    let config = write_config_with(
+        &dir,
+        &server.url,
+        &format!(
+            "[targets.share]\ntype = \"directory\"\npath = \"{}\"\n\n[reports.targets]\ndefault = [\"share\"]\n",
+            share.display()
+        ),
+    );
+
+    let output = run(
+        &config,
+        &[
+            "report",
+            "absences",
+            "--upload",
+            "{year}/Abwesenheiten.csv",
+            "--format",
+            "csv",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    let year = Local::now().format("%Y").to_string();
+    let uploaded = fs::read_to_string(share.join(year).join("Abwesenheiten.csv")).unwrap();
+    assert_eq!(uploaded, absences_csv(&config, &dir));
+}
+
+#[test]
+fn targets_are_chosen_per_report() {
+    let server = absences_server();
+    let dir = tempdir().unwrap();
+    let archive = dir.path().join("archive");
+    let share = dir.path().join("share");
+    let config = write_config_with(
+        &dir,
+        &server.url,
+        &format!(
+            r#"
+[targets.archive]
+type = "directory"
+path = "{}"
+
+[targets.share]
+type = "directory"
+path = "{}"
+
+[reports.targets]
+default = ["share"]
+absences = ["archive", "share"]
+"#,
+            archive.display(),
+            share.display()
+        ),
+    );
+
+    let output = run(&config, &UPLOAD_CSV);
+    assert!(output.status.success(), "{output:?}");
+    let output = run(
+        &config,
+        &[
+            "report",
+            "coverage",
+            "--upload",
+            "Abdeckung.csv",
+            "--format",
+            "csv",
+            "--last-month",
+        ],
+    );
+    assert!(output.status.success(), "{output:?}");
+
+    assert!(archive.join("Abwesenheiten 2025.csv").exists());
+    assert!(!archive.join("Abdeckung.csv").exists());
+    assert!(share.join("Abwesenheiten 2025.csv").exists());
+    assert!(share.join("Abdeckung.csv").exists());
+}
+
+#[test]
+fn unknown_targets_are_rejected() {
+    let dir = tempdir().unwrap();
+    let config = write_config_with(
+        &dir,
+        "http://127.0.0.1:1/api/",
+        "[reports.targets]\nabsences = [\"nas\"]\n",
+    );
+
+    let output = run(&config, &UPLOAD_CSV);
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Unknown upload target \"nas\""), "{stderr}");
+}
+
+#[test]
+fn reports_are_put_into_an_s3_bucket() {
+    let server = absences_server();
+    let storage = StubServer::start(vec![(
+        "/reports/monatlich/Abwesenheiten%202025.csv",
+        Response::new(200, ""),
+    )]);
+    let dir = tempdir().unwrap();
+    let config = write_config_with(
+        &dir,
+        &server.url,
+        &format!(
+            r#"
+[targets.minio]
+type = "s3"
+endpoint = "{}"
+bucket = "reports"
+region = "eu-central-1"
+access_key_id = "minio"
+password = "minio-secret"
+prefix = "monatlich/"
+
+[reports.targets]
+absences = ["minio"]
+"#,
+            storage.url.trim_end_matches("api/")
+        ),
+    );
+
+    let output = run(&config, &UPLOAD_CSV);
+
+    assert!(output.status.success(), "{output:?}");
+    let requests = storage.requests();
+    assert_eq!(requests.len(), 1);
+    let request = &requests[0];
+    assert_eq!(request.method, "PUT");
+    assert_eq!(request.body, absences_csv(&config, &dir));
+    let payload_hash: String = Sha256::digest(request.body.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    assert_eq!(request.header("x-amz-content-sha256"), Some(&*payload_hash));
+    let authorization = request.header("authorization").unwrap();
+    assert!(
+        authorization.starts_with("AWS4-HMAC-SHA256 Credential=minio/"),
+        "{authorization}"
+    );
+    assert!(
+        authorization.contains(
+            "/eu-central-1/s3/aws4_request, SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature="
+        ),
+        "{authorization}"
+    );
+}
+
+#[test]
+fn rejected_s3_uploads_fail() {
+    let server = absences_server();
+    let storage = StubServer::start(vec![(
+        "/reports/Abwesenheiten%202025.csv",
+        Response::new(403, "<Error><Code>AccessDenied</Code></Error>"),
+    )]);
+    let dir = tempdir().unwrap();
+    let config = write_config_with(
+        &dir,
+        &server.url,
+        &format!(
+            "[targets.minio]\ntype = \"s3\"\nendpoint = \"{}\"\nbucket = \"reports\"\naccess_key_id = \"minio\"\npassword = \"wrong\"\n\n[reports.targets]\ndefault = [\"minio\"]\n",
+            storage.url.trim_end_matches("api/")
+        ),
+    );
+
+    let output = run(&config, &UPLOAD_CSV);
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("to \"minio\""), "{stderr}");
+    assert!(stderr.contains("AccessDenied"), "{stderr}");
+}
+
+#[cfg(unix)]
+#[test]
+fn sftp_is_run_in_batch_mode() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let server = absences_server();
+    let dir = tempdir().unwrap();
+    // Stand-in for sftp which records its arguments and commands
+    let program = dir.path().join("sftp");
+    fs::write(
+        &program,
+        format!(
+            "#!/bin/sh\necho \"$@\" > {0}/args\ncat > {0}/batch\n",
+            dir.path().display()
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&program, fs::Permissions::from_mode(0o755)).unwrap();
+    let config = write_config_with(
+        &dir,
+        &server.url,
+        &format!(
+            r#"
+[targets.nas]
+type = "sftp"
+host = "nas.local"
+port = 2222
+username = "reports"
+directory = "/srv/reports"
+identity_file = "/etc/divera-reports/id_ed25519"
+program = "{}"
+
+[reports.targets]
+default = ["nas"]
+"#,
+            program.display()
+        ),
+    );
+
+    let output = run(&config, &UPLOAD_CSV);
+
+    assert!(output.status.success(), "{output:?}");
+    let args = fs::read_to_string(dir.path().join("args")).unwrap();
+    assert_eq!(
+        args.trim(),
+        "-b - -P 2222 -i /etc/divera-reports/id_ed25519 reports@nas.local"
+    );
+    let batch = fs::read_to_string(dir.path().join("batch")).unwrap();
+    let lines: Vec<&str> = batch.lines().collect();
+    assert_eq!(lines[..2], ["-mkdir \"/srv\"", "-mkdir \"/srv/reports\""]);
+    assert!(lines[2].starts_with("put \""), "{batch}");
+    assert!(
+        lines[2].ends_with("Abwesenheiten 2025.csv\" \"/srv/reports/Abwesenheiten 2025.csv\""),
+        "{batch}"
+    );
+}
+
+/// Config uploading into the share directory and keeping fingerprints in the temporary directory
+fn write_fingerprint_config(dir: &TempDir, divera_url: &str) -> PathBuf {
+    write_config_with(
+        dir,
+        divera_url,
+        &format!(
+            "[targets.share]\ntype = \"directory\"\npath = \"{}\"\n\n[reports]\nenabled = [\"absences\"]\nfingerprints = \"{}\"\n\n[reports.targets]\ndefault = [\"share\"]\n",
+            dir.path().join("share").display(),
+            dir.path().join("fingerprints.json").display()
+        ),
+    )
+}
+
+#[test]
+fn unchanged_reports_are_not_uploaded_again() {
+    let server = absences_server();
+    let dir = tempdir().unwrap();
+    let uploaded = dir.path().join("share").join("Abwesenheiten 2025.csv");
+    let config = write_fingerprint_config(&dir, &server.url);
+
+    let output = run(&config, &UPLOAD_CSV);
+    assert!(output.status.success(), "{output:?}");
+    assert!(uploaded.exists());
+    let fingerprints = fs::read_to_string(dir.path().join("fingerprints.json")).unwrap();
+    assert!(
+        fingerprints.contains("Abwesenheiten 2025.csv"),
+        "{fingerprints}"
+    );
+
+    fs::remove_file(&uploaded).unwrap();
+    let output = run(&config, &UPLOAD_CSV);
+    assert!(output.status.success(), "{output:?}");
+    assert!(!uploaded.exists());
+
+    // The skipped report of the malformed absences changes the content
+    let server = malformed_absences_server();
+    let config = write_fingerprint_config(&dir, &server.url);
+    let mut args = UPLOAD_CSV.to_vec();
+    args.extend(["--max-errors", "1"]);
+    let output = run(&config, &args);
+    assert!(output.status.success(), "{output:?}");
+    assert!(uploaded.exists());
+}
+
+#[test]
+fn unchanged_files_of_all_reports_are_not_uploaded_again() {
+    let server = absences_server();
+    let dir = tempdir().unwrap();
+    let config = write_fingerprint_config(&dir, &server.url);
+    let share = dir.path().join("share");
+
+    let output = run(&config, &["report", "all", "--upload"]);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(fs::read_dir(&share).unwrap().count(), 1);
+
+    fs::remove_dir_all(&share).unwrap();
+    let output = run(&config, &["report", "all", "--upload"]);
+    assert!(output.status.success(), "{output:?}");
+    assert!(!share.exists());
+
+    let output = run(
+        &config,
+        &["report", "all", "--upload", "--combined", "Berichte.xlsx"],
+    );
+    assert!(output.status.success(), "{output:?}");
+    assert!(share.join("Berichte.xlsx").exists());
+}
+
+#[test]
+fn archived_copies_are_kept_and_expired_ones_removed() {
+    let server = absences_server();
+    let dir = tempdir().unwrap();
+    let share = dir.path().join("share");
+    let archive = share.join("archiv");
+    let today = Local::now().date_naive();
+    let recent = format!(
+        "Abwesenheiten_{}.csv",
+        (today - Days::new(10)).format("%Y-%m-%d")
+    );
+    fs::create_dir_all(&archive).unwrap();
+    for name in ["Abwesenheiten_2000-01-01.csv", &recent, "Notizen.txt"] {
+        fs::write(archive.join(name), "").unwrap();
+    }
+    let config = write_config_with(
+        &dir,
+        &server.url,
+        &format!(
+            r#"
+[targets.share]
+type = "directory"
+path = "{}"
+
+[reports.targets]
+default = ["share"]
+
+[reports.archive]
+directory = "archiv"
+retention_days = 30
+"#,
+            share.display()
+        ),
+    );
+
+    let output = run(
+        &config,
+        &[
+            "report",
+            "absences",
+            "--upload",
+            "{title}.csv",
+            "--format",
+            "csv",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    assert!(share.join("Abwesenheiten.csv").exists());
+    let mut archived: Vec<String> = fs::read_dir(&archive)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    archived.sort();
+    let mut expected = vec![
+        format!("Abwesenheiten_{}.csv", today.format("%Y-%m-%d")),
+        recent,
+        "Notizen.txt".to_string(),
+    ];
+    expected.sort();
+    assert_eq!(archived, expected);
+}
+
+#[test]
+fn copies_of_other_reports_in_the_archive_are_kept() {
+    let server = absences_server();
+    let dir = tempdir().unwrap();
+    let share = dir.path().join("share");
+    let archive = share.join("archiv");
+    fs::create_dir_all(&archive).unwrap();
+    for name in [
+        "Abwesenheiten_2000-01-01.csv",
+        "Dienstplan_2000-01-01.csv",
+        "Abwesenheiten_2000-01-01.xlsx",
+    ] {
+        fs::write(archive.join(name), "").unwrap();
+    }
+    let config = write_config_with(
+        &dir,
+        &server.url,
+        &format!(
+            r#"
+[targets.share]
+type = "directory"
+path = "{}"
+
+[reports.targets]
+default = ["share"]
+
+[reports.archive]
+directory = "archiv"
+retention_days = 30
+"#,
+            share.display()
+        ),
+    );
+
+    let output = run(
+        &config,
+        &[
+            "report",
+            "absences",
+            "--upload",
+            "{title}.csv",
+            "--format",
+            "csv",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    assert!(!archive.join("Abwesenheiten_2000-01-01.csv").exists());
+    assert!(archive.join("Dienstplan_2000-01-01.csv").exists());
+    assert!(archive.join("Abwesenheiten_2000-01-01.xlsx").exists());
+}
+
+#[test]
+fn expired_copies_are_removed_from_s3() {
+    let server = absences_server();
+    let today = Local::now().date_naive().format("%Y-%m-%d");
+    let archived = format!("/reports/archive/Abwesenheiten_{today}.csv");
+    let storage = StubServer::start(vec![
+        ("/reports/Abwesenheiten.csv", Response::new(200, "")),
+        (&archived, Response::new(200, "")),
+        (
+            "/reports?list-type=2",
+            Response::new(
+                200,
+                &format!(
+                    r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListBucketResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+  <Name>reports</Name>
+  <IsTruncated>false</IsTruncated>
+  <Contents><Key>archive/Abwesenheiten_2000-01-01.csv</Key></Contents>
+  <Contents><Key>archive/Abwesenheiten_{today}.csv</Key></Contents>
+  <Contents><Key>archive/2000/Abwesenheiten_2000-01-01.csv</Key></Contents>
+</ListBucketResult>"#
+                ),
+            ),
+        ),
+        (
+            "/reports/archive/Abwesenheiten_2000-01-01.csv",
+            Response::new(204, ""),
+        ),
+    ]);
+    let dir = tempdir().unwrap();
+    let config = write_config_with(
+        &dir,
+        &server.url,
+        &format!(
+            "[targets.minio]\ntype = \"s3\"\nendpoint = \"{}\"\nbucket = \"reports\"\naccess_key_id = \"minio\"\npassword = \"minio-secret\"\n\n[reports.targets]\ndefault = [\"minio\"]\n\n[reports.archive]\nretention_days = 365\n",
+            storage.url.trim_end_matches("api/")
+        ),
+    );
+
+    let output = run(
+        &config,
+        &[
+            "report",
+            "absences",
+            "--upload",
+            "Abwesenheiten.csv",
+            "--format",
+            "csv",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    let requests: Vec<(String, String, String)> = storage
+        .requests()
+        .into_iter()
+        .map(|request| (request.method, request.path, request.query))
+        .collect();
+    assert_eq!(
+        requests,
+        [
+            ("PUT", "/reports/Abwesenheiten.csv", ""),
+            ("PUT", archived.as_str(), ""),
+            ("GET", "/reports", "list-type=2&prefix=archive%2F"),
+            (
+                "DELETE",
+                "/reports/archive/Abwesenheiten_2000-01-01.csv",
+                ""
+            ),
+        ]
+        .map(|(method, path, query)| (
+            method.to_string(),
+            path.to_string(),
+            query.to_string()
+        ))
+    );
+}
+
+/// Config mailing the absences via the sink and keeping fingerprints in the temporary directory
+fn write_mail_config(dir: &TempDir, divera_url: &str, sink: &SmtpSink) -> PathBuf {
+    write_config_with(
+        dir,
+        divera_url,
+        &format!(
+            "[targets.gruppenfuehrer]\ntype = \"smtp\"\nhost = \"127.0.0.1\"\nport = {}\nsecurity = \"none\"\nfrom = \"berichte@example.org\"\nrecipients = [\"a@example.org\", \"b@example.org\"]\nsubject = \"{{title}} vom {{date}}\"\n\n[reports]\nenabled = [\"absences\"]\nfingerprints = \"{}\"\n\n[reports.targets]\nabsences = [\"gruppenfuehrer\"]\n",
+            sink.port,
+            dir.path().join("fingerprints.json").display()
+        ),
+    )
+}
+
+#[test]
+fn reports_are_mailed_with_new_entries() {
+    let server = absences_server();
+    let sink = SmtpSink::start();
+    let dir = tempdir().unwrap();
+    let config = write_mail_config(&dir, &server.url, &sink);
+
+    let output = run(&config, &UPLOAD_CSV);
+
+    assert!(output.status.success(), "{output:?}");
+    let mails = sink.mails();
+    assert_eq!(mails.len(), 1);
+    let mail = &mails[0];
+    assert_eq!(mail.from, "berichte@example.org");
+    assert_eq!(mail.recipients, vec!["a@example.org", "b@example.org"]);
+    let today = Local::now().date_naive().format("%Y-%m-%d").to_string();
+    assert!(
+        mail.data
+            .contains(&format!("Subject: Abwesenheiten vom {today}")),
+        "{}",
+        mail.data
+    );
+    assert!(
+        mail.data.contains("Content-Type: text/csv"),
+        "{}",
+        mail.data
+    );
+    assert!(
+        mail.data.contains("filename=\"Abwesenheiten 2025.csv\""),
+        "{}",
+        mail.data
+    );
+    assert!(mail.data.contains("2 neue Eintr"), "{}", mail.data);
+    assert!(mail.data.contains("Mustermann, Max"), "{}", mail.data);
+}
+
+#[test]
+fn mails_only_list_entries_which_were_not_sent_before() {
+    let dir = tempdir().unwrap();
+    let sink = SmtpSink::start();
+    let server = absences_server();
+    let config = write_mail_config(&dir, &server.url, &sink);
+    let output = run(&config, &UPLOAD_CSV);
+    assert!(output.status.success(), "{output:?}");
+
+    // Unchanged reports are not mailed again
+    let output = run(&config, &UPLOAD_CSV);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(sink.mails().len(), 1);
+
+    // Only the known report 101 remains of the malformed absences
+    let server = malformed_absences_server();
+    let config = write_mail_config(&dir, &server.url, &sink);
+    let mut args = UPLOAD_CSV.to_vec();
+    args.extend(["--max-errors", "1"]);
+    let output = run(&config, &args);
+    assert!(output.status.success(), "{output:?}");
+    let mails = sink.mails();
+    assert_eq!(mails.len(), 2);
+    assert!(
+        mails[1].data.contains("keine neuen Eintr"),
+        "{}",
+        mails[1].data
+    );
+}
+
+#[test]
+fn mails_require_fingerprints() {
+    let server = absences_server();
+    let sink = SmtpSink::start();
+    let dir = tempdir().unwrap();
+    let config = write_config_with(
+        &dir,
+        &server.url,
+        &format!(
+            "[targets.gruppenfuehrer]\ntype = \"smtp\"\nhost = \"127.0.0.1\"\nport = {}\nsecurity = \"none\"\nfrom = \"berichte@example.org\"\nrecipients = [\"a@example.org\"]\n\n[reports.targets]\nabsences = [\"gruppenfuehrer\"]\n",
+            sink.port
+        ),
+    );
+
+    let output = run(&config, &UPLOAD_CSV);
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("needs a fingerprints file"), "{stderr}");
+    assert!(sink.mails().is_empty());
+}
+
+#[test]
+fn fingerprints_of_former_versions_are_read() {
+    let server = absences_server();
+    let dir = tempdir().unwrap();
+    let uploaded = dir.path().join("share").join("Abwesenheiten 2025.csv");
+    let config = write_fingerprint_config(&dir, &server.url);
+    let output = run(&config, &UPLOAD_CSV);
+    assert!(output.status.success(), "{output:?}");
+
+    // Former versions only kept the fingerprint of the content
+    let path = dir.path().join("fingerprints.json");
+    let mut fingerprints: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    let upload = &mut fingerprints["share"]["Abwesenheiten 2025.csv"];
+    *upload = upload["fingerprint"].clone();
+    assert!(upload.is_string());
+    fs::write(&path, fingerprints.to_string()).unwrap();
+    fs::remove_file(&uploaded).unwrap();
+
+    let output = run(&config, &UPLOAD_CSV);
+    assert!(output.status.success(), "{output:?}");
+    assert!(!uploaded.exists());
+}
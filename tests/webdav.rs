@@ -0,0 +1,280 @@
+mod common;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Output,
+    time::{Duration, Instant},
+};
+
+use chrono::Local;
+use common::{
+    absences_server, run, write_config, write_config_with, write_config_with_divera, Response,
+    StubServer,
+};
+use tempfile::{tempdir, TempDir};
+
+const ROOT: &str = "/remote.php/dav/files/user";
+const FILE: &str = "/remote.php/dav/files/user/Berichte/Abwesenheiten.csv";
+
+/// Stub serving the absences of the fixtures and a webdav server below [`ROOT`]
+fn webdav_server(routes: Vec<(&str, Response)>) -> StubServer {
+    let mut all = vec![
+        ("/api/v2/auth/login", Response::fixture("login.json")),
+        ("/api/v2/pull/all", Response::fixture("pull_all.json")),
+        (
+            "/api/v2/reporttypes/reports",
+            Response::fixture("reports_absences.json"),
+        ),
+    ];
+    all.extend(routes);
+    StubServer::start(all)
+}
+
+/// Config uploading into the `Berichte` directory of the stub
+fn write_webdav_config(dir: &TempDir, server: &StubServer) -> PathBuf {
+    let root = server.url.replace("/api/", ROOT);
+    write_config_with(
+        dir,
+        &server.url,
+        &format!(
+            "[targets.nextcloud]\ntype = \"webdav\"\nusername = \"user\"\npassword = \"secret\"\nroot_directory = \"{root}/Berichte\"\n\n[reports.targets]\ndefault = [\"nextcloud\"]\n"
+        ),
+    )
+}
+
+fn multistatus(size: usize, etag: &str) -> Response {
+    Response::new(
+        207,
+        &format!(
+            r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:">
+  <d:response>
+    <d:href>{FILE}</d:href>
+    <d:propstat>
+      <d:prop><d:getcontentlength>{size}</d:getcontentlength><d:getetag>&quot;{etag}&quot;</d:getetag></d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+  </d:response>
+</d:multistatus>"#
+        ),
+    )
+}
+
+/// Size of the absences csv as uploaded
+fn absences_size() -> usize {
+    let server = absences_server();
+    let dir = tempdir().unwrap();
+    let config = write_config(&dir, &server.url);
+    let path = dir.path().join("expected.csv");
+    let output = run(
+        &config,
+        &[
+            "report",
+            "absences",
+            "--write",
+            path.to_str().unwrap(),
+            "--format",
+            "csv",
+        ],
+    );
+    assert!(output.status.success(), "{output:?}");
+    fs::metadata(path).unwrap().len() as usize
+}
+
+fn upload(config: &Path) -> Output {
+    run(
+        config,
+        &[
+            "report",
+            "absences",
+            "--upload",
+            "Abwesenheiten.csv",
+            "--format",
+            "csv",
+        ],
+    )
+}
+
+#[test]
+fn missing_collections_are_created_and_the_upload_is_verified() {
+    let size = absences_size();
+    let server = webdav_server(vec![
+        (FILE, Response::new(409, "")),
+        (FILE, Response::new(201, "").with_header("ETag", "\"5f3e\"")),
+        (FILE, multistatus(size, "5f3e")),
+        (
+            "/remote.php/dav/files/user/Berichte",
+            Response::new(409, ""),
+        ),
+        (
+            "/remote.php/dav/files/user/Berichte",
+            Response::new(201, ""),
+        ),
+        (ROOT, Response::new(405, "")),
+    ]);
+    let dir = tempdir().unwrap();
+    let config = write_webdav_config(&dir, &server);
+
+    let output = upload(&config);
+
+    assert!(output.status.success(), "{output:?}");
+    let requests: Vec<(String, String)> = server
+        .requests()
+        .into_iter()
+        .filter(|request| request.path.starts_with(ROOT))
+        .map(|request| (request.method, request.path))
+        .collect();
+    let expected = [
+        ("PUT", FILE),
+        ("MKCOL", "/remote.php/dav/files/user/Berichte"),
+        ("MKCOL", ROOT),
+        ("MKCOL", "/remote.php/dav/files/user/Berichte"),
+        ("PUT", FILE),
+        ("PROPFIND", FILE),
+    ];
+    assert_eq!(
+        requests,
+        expected.map(|(method, path)| (method.to_string(), path.to_string()))
+    );
+    let propfind = server.requests_to(FILE).pop().unwrap();
+    assert_eq!(propfind.header("depth"), Some("0"));
+    assert!(propfind
+        .header("authorization")
+        .unwrap()
+        .starts_with("Basic "));
+}
+
+#[test]
+fn incomplete_uploads_are_detected() {
+    let server = webdav_server(vec![
+        (FILE, Response::new(201, "")),
+        (FILE, multistatus(3, "5f3e")),
+    ]);
+    let dir = tempdir().unwrap();
+    let config = write_webdav_config(&dir, &server);
+
+    let output = upload(&config);
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("has 3 bytes instead of"), "{stderr}");
+}
+
+#[test]
+fn hanging_servers_time_out() {
+    let server = webdav_server(vec![(
+        FILE,
+        Response::new(201, "").with_delay(Duration::from_secs(10)),
+    )]);
+    let dir = tempdir().unwrap();
+    let root = server.url.replace("/api/", ROOT);
+    let config = write_config_with_divera(
+        &dir,
+        &server.url,
+        "timeout_secs = 1",
+        &format!(
+            "[targets.nextcloud]\ntype = \"webdav\"\nusername = \"user\"\npassword = \"secret\"\nroot_directory = \"{root}/Berichte\"\n\n[reports.targets]\ndefault = [\"nextcloud\"]\n"
+        ),
+    );
+
+    let start = Instant::now();
+    let output = upload(&config);
+
+    assert!(!output.status.success());
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn failed_uploads_report_the_status_and_message() {
+    let server = webdav_server(vec![(
+        FILE,
+        Response::new(
+            507,
+            r#"<?xml version="1.0"?><d:error xmlns:d="DAV:" xmlns:s="http://sabredav.org/ns"><s:message>Quota exceeded</s:message></d:error>"#,
+        ),
+    )]);
+    let dir = tempdir().unwrap();
+    let config = write_webdav_config(&dir, &server);
+
+    let output = upload(&config);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("no storage left for PUT"), "{stderr}");
+
+    let server = webdav_server(vec![(
+        FILE,
+        Response::new(
+            423,
+            r#"<?xml version="1.0"?><d:error xmlns:d="DAV:" xmlns:s="http://sabredav.org/ns"><s:message>File is locked</s:message></d:error>"#,
+        ),
+    )]);
+    let config = write_webdav_config(&dir, &server);
+
+    let output = upload(&config);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("answered PUT") && stderr.contains("423 Locked: File is locked"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn rejected_credentials_are_reported() {
+    let server = webdav_server(vec![(FILE, Response::new(401, ""))]);
+    let dir = tempdir().unwrap();
+    let config = write_webdav_config(&dir, &server);
+
+    let output = upload(&config);
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("rejected the credentials"), "{stderr}");
+    assert_eq!(server.requests_to(FILE).len(), 1);
+}
+
+#[test]
+fn expired_copies_are_removed_from_the_archive_collection() {
+    let size = absences_size();
+    let archive = "/remote.php/dav/files/user/Berichte/archive/";
+    let today = Local::now().date_naive().format("%Y-%m-%d");
+    let archived = format!("{archive}Abwesenheiten_{today}.csv");
+    let expired = format!("{archive}Abwesenheiten_2000-01-01.csv");
+    let other = format!("{archive}Abwesenheiten%202000-01-01_2000-01-01.csv");
+    let listing = format!(
+        r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:">
+  <d:response><d:href>{archive}</d:href><d:propstat><d:prop><d:resourcetype><d:collection/></d:resourcetype></d:prop></d:propstat></d:response>
+  <d:response><d:href>{archive}2000_2000-01-01/</d:href><d:propstat><d:prop><d:resourcetype><d:collection/></d:resourcetype></d:prop></d:propstat></d:response>
+  <d:response><d:href>{expired}</d:href><d:propstat><d:prop><d:resourcetype/></d:prop></d:propstat></d:response>
+  <d:response><d:href>{other}</d:href><d:propstat><d:prop><d:resourcetype/></d:prop></d:propstat></d:response>
+  <d:response><d:href>{archived}</d:href><d:propstat><d:prop><d:resourcetype/></d:prop></d:propstat></d:response>
+</d:multistatus>"#
+    );
+    let server = webdav_server(vec![
+        (FILE, Response::new(201, "")),
+        (FILE, multistatus(size, "5f3e")),
+        (&archived, Response::new(201, "")),
+        (&archived, multistatus(size, "5f3f")),
+        (archive, Response::new(207, &listing)),
+        (&expired, Response::new(204, "")),
+        (&other, Response::new(204, "")),
+    ]);
+    let dir = tempdir().unwrap();
+    let config = write_webdav_config(&dir, &server);
+    let mut content = fs::read_to_string(&config).unwrap();
+    content.push_str("\n[reports.archive]\nretention_days = 90\n");
+    fs::write(&config, content).unwrap();
+
+    let output = upload(&config);
+
+    assert!(output.status.success(), "{output:?}");
+    let listing = server.requests_to(archive);
+    assert_eq!(listing.len(), 1);
+    assert_eq!(listing[0].header("depth"), Some("1"));
+    let deleted = server.requests_to(&expired);
+    assert_eq!(deleted.len(), 1);
+    assert_eq!(deleted[0].method, "DELETE");
+    assert!(server.requests_to(&other).is_empty());
+}